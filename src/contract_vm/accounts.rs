@@ -0,0 +1,167 @@
+//named accounts registry, `@alice` in messages is expanded to the address of account alice
+
+use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::rc::Rc;
+use cosmwasm_std::{Coin, HumanAddr, Uint128};
+use serde_json::Value;
+
+#[derive(Clone, Debug)]
+pub struct Account {
+    pub alias : String,
+    pub address : HumanAddr,
+    pub balance : Vec<Coin>,
+}
+
+//account registry of one instance, alias => account
+//shared by storage, querier and engine so that their output can show addresses by alias
+#[derive(Clone, Debug, Default)]
+pub struct Accounts {
+    registry : Rc<RefCell<BTreeMap<String,Account>>>,
+}
+
+//parse coins like `100okt,5uatom`
+pub fn parse_coins(text : &str) -> Result<Vec<Coin>,String>{
+    let mut coins = Vec::new();
+    for item in text.split(',') {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        let pos = match item.find(|c : char| !c.is_ascii_digit()){
+            None => return Err(format!("coin [{}] has no denom",item)),
+            Some(0) => return Err(format!("coin [{}] has no amount",item)),
+            Some(p) => p
+        };
+        let amount = match item[..pos].parse::<u128>(){
+            Err(e) => return Err(format!("coin [{}] has wrong amount, {}",item,e)),
+            Ok(a) => a
+        };
+        coins.push(Coin{
+            denom: item[pos..].to_string(),
+            amount: Uint128(amount)
+        });
+    }
+    return Ok(coins);
+}
+
+pub fn format_coins(coins : &[Coin]) -> String{
+    let items : Vec<String> = coins.iter().map(|c| format!("{}{}",c.amount,c.denom)).collect();
+    return items.join(",");
+}
+
+fn is_word(c : char) -> bool{
+    return c.is_alphanumeric() || c == '_';
+}
+
+//replace every known address standing as a whole word in text by `@alias`, addresses inside hex or keys stay as they are
+pub fn label(accounts : &Accounts,text : &str) -> String{
+    let registry = accounts.registry.borrow();
+    let mut labels : Vec<(&str,&str)> = registry.values()
+        .filter(|a| !a.address.as_str().is_empty())
+        .map(|a| (a.address.as_str(),a.alias.as_str())).collect();
+    if labels.is_empty() {
+        return text.to_string();
+    }
+    //longer addresses first, so an address containing another one is labeled as a whole
+    labels.sort_by(|a,b| b.0.len().cmp(&a.0.len()));
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    let mut after_word = false;
+    'scan: while pos < text.len() {
+        if !after_word {
+            for (address,alias) in &labels {
+                if !text[pos..].starts_with(address) {
+                    continue;
+                }
+                let end = pos + address.len();
+                if text[end..].chars().next().map(is_word).unwrap_or(false) {
+                    continue;
+                }
+                out.push('@');
+                out.push_str(alias);
+                pos = end;
+                after_word = true;
+                continue 'scan;
+            }
+        }
+        let c = text[pos..].chars().next().unwrap_or_default();
+        out.push(c);
+        after_word = is_word(c);
+        pos += c.len_utf8();
+    }
+    return out;
+}
+
+impl Accounts {
+    pub fn new() -> Self{
+        return Accounts::default();
+    }
+
+    pub fn add(&mut self,alias : &str,address : HumanAddr,balance : Vec<Coin>){
+        self.registry.borrow_mut().insert(alias.to_string(),Account{
+            alias: alias.to_string(),
+            address,
+            balance
+        });
+    }
+
+    pub fn get(&self,alias : &str) -> Option<Account>{
+        return self.registry.borrow().get(alias.trim_start_matches('@')).cloned();
+    }
+
+    pub fn all(&self) -> Vec<Account>{
+        return self.registry.borrow().values().cloned().collect();
+    }
+
+    //alias (with or without `@`) or a plain address
+    pub fn resolve(&self,name : &str) -> HumanAddr{
+        return match self.get(name) {
+            Some(a) => a.address.clone(),
+            None => HumanAddr::from(name)
+        };
+    }
+
+    fn expand_value(&self,value : &mut Value) -> Result<(),String>{
+        match value {
+            Value::String(s) => {
+                if s.starts_with("@@") {
+                    //escaped, `"@@home"` is sent as `"@home"`
+                    s.remove(0);
+                }else if s.starts_with('@') {
+                    match self.get(s.as_str()) {
+                        None => return Err(format!("unknown account {}, write @@ to send a text starting with @",s)),
+                        Some(a) => *s = a.address.to_string()
+                    }
+                }
+            },
+            Value::Array(arr) => {
+                for v in arr {
+                    self.expand_value(v)?;
+                }
+            },
+            Value::Object(obj) => {
+                for (_,v) in obj.iter_mut() {
+                    self.expand_value(v)?;
+                }
+            },
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    //expand every `"@alias"` string in json message into the address of that account, `"@@"` escapes `"@"`
+    //an unknown alias is an error so a typo is not sent as text
+    pub fn expand(&self,json_msg : &str) -> Result<String,String>{
+        if !json_msg.contains("\"@") {
+            return Ok(json_msg.to_string());
+        }
+        let mut value : Value = match serde_json::from_str(json_msg){
+            //not json, leave it to the contract to complain
+            Err(_e) => return Ok(json_msg.to_string()),
+            Ok(v) => v
+        };
+        self.expand_value(&mut value)?;
+        return Ok(value.to_string());
+    }
+}
@@ -1,8 +1,10 @@
 //analyzer for json schema file
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::contract_vm::{validator, watcher};
 
 //Todo: analyze more detail from json schema file
 pub struct StructType {
@@ -15,10 +17,51 @@ pub struct Member{
     pub member_def : String,
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SchemaRole {
+    Init,
+    Handle,
+    Query,
+    Migrate,
+    Response,
+    State,
+}
+
+impl SchemaRole {
+    pub fn of_title(title : &str) -> SchemaRole{
+        return match title {
+            "InitMsg" | "InstantiateMsg" => SchemaRole::Init,
+            "HandleMsg" | "ExecuteMsg" => SchemaRole::Handle,
+            "QueryMsg" => SchemaRole::Query,
+            "MigrateMsg" => SchemaRole::Migrate,
+            _ => {
+                if title.ends_with("Response") {
+                    SchemaRole::Response
+                }else {
+                    SchemaRole::State
+                }
+            }
+        };
+    }
+
+    pub fn of_call_type(call_type : &str) -> Option<SchemaRole>{
+        return match call_type {
+            "init" => Some(SchemaRole::Init),
+            "handle" => Some(SchemaRole::Handle),
+            "query" => Some(SchemaRole::Query),
+            "migrate" => Some(SchemaRole::Migrate),
+            _ => None
+        };
+    }
+}
+
 pub struct Analyzer{
     pub map_of_basetype : HashMap<String,String>,
     pub map_of_struct : HashMap<String,HashMap<String,String>>,
-    pub map_of_member : HashMap<String,HashMap<String,Vec<Member>>>
+    pub map_of_member : HashMap<String,HashMap<String,Vec<Member>>>,
+    pub map_of_schema : HashMap<String,serde_json::Value>,
+    pub map_of_response : HashMap<String,String>,
+    pub map_of_role : BTreeMap<String,SchemaRole>
 }
 
 impl Analyzer{
@@ -26,7 +69,10 @@ impl Analyzer{
         return Analyzer{
             map_of_basetype: HashMap::new(),
             map_of_struct: HashMap::new(),
-            map_of_member: HashMap::new()
+            map_of_member: HashMap::new(),
+            map_of_schema: HashMap::new(),
+            map_of_response: HashMap::new(),
+            map_of_role: BTreeMap::new()
         }
     }
 
@@ -99,30 +145,30 @@ impl Analyzer{
     }
 
     pub fn dump_all_definitions(&self){
-        println!("Base Type :");
+        watcher::logger_info("Base Type :");
         for b in &self.map_of_basetype {
-            println!("{} => {}",b.0,b.1);
+            watcher::logger_info(&format!("{} => {}",b.0,b.1));
         }
-        println!("Struct Type :");
+        watcher::logger_info("Struct Type :");
         for s in &self.map_of_struct {
-            println!("{} {{",s.0);
+            watcher::logger_info(&format!("{} {{",s.0));
             for member in s.1 {
-                println!("\t{} : {}",member.0,member.1);
+                watcher::logger_info(&format!("\t{} : {}",member.0,member.1));
             }
-            println!("}}");
+            watcher::logger_info("}");
         }
     }
 
     pub fn dump_all_members(&self){
         for b in &self.map_of_member {
-            println!("{} {{",b.0);
+            watcher::logger_info(&format!("{} {{",b.0));
             for vcm in b.1 {
-                println!("{} {{",vcm.0);
+                watcher::logger_info(&format!("{} {{",vcm.0));
                 for vc in vcm.1 {
-                    println!("\t{} : {}",vc.member_name,vc.member_def);
+                    watcher::logger_info(&format!("\t{} : {}",vc.member_name,vc.member_def));
                 }
             }
-            println!("}}")
+            watcher::logger_info("}")
         }
     }
 
@@ -200,6 +246,8 @@ impl Analyzer{
             Some(kvs) => kvs,
         };
 
+        self.map_of_schema.insert(title_must_exist.to_string(),translated.clone());
+        self.map_of_role.insert(title_must_exist.to_string(),SchemaRole::of_title(title_must_exist));
         self.map_of_member.insert(title_must_exist.to_string(),HashMap::new());
         let mut current_member = match self.map_of_member.get_mut(&title_must_exist.to_string()){
             None => return false,
@@ -260,26 +308,223 @@ impl Analyzer{
         return true;
     }
 
-    //load jsonschema file, translate from json string to func:params...
-    pub fn try_load_json_schema(&mut self,dir : String) -> bool{
+    //title of the message schema used by call type, like InitMsg for init
+    //roles are sorted by title, so the same title is chosen every run if schemas of both names are loaded
+    pub fn message_title_of(&self,call_type : &str) -> Option<String>{
+        let role = match SchemaRole::of_call_type(call_type){
+            None => return None,
+            Some(r) => r
+        };
+        for (title,r) in &self.map_of_role {
+            if *r == role {
+                return Some(title.clone());
+            }
+        }
+        return None;
+    }
+
+    //message with variants is an enum like HandleMsg, otherwise a struct like InitMsg
+    pub fn is_enum_message(&self,title : &str) -> bool{
+        return match self.map_of_schema.get(title){
+            None => false,
+            Some(s) => s.get("anyOf").is_some() || s.get("oneOf").is_some()
+        };
+    }
+
+    pub fn dump_all_roles(&self){
+        for (title,role) in &self.map_of_role {
+            watcher::logger_info(&format!("{} => {:?}",title,role));
+        }
+    }
+
+    //check json message against schema of call type, skipped if no schema loaded
+    pub fn validate_message(&self,call_type : &str,json_msg : &str) -> Result<(),Vec<String>>{
+        let value : serde_json::Value = match serde_json::from_str(json_msg){
+            Ok(v) => v,
+            Err(e) => return Err(vec![format!("{}: invalid json, {}",call_type,e)])
+        };
+        let schema = match self.message_title_of(call_type).and_then(|t| self.map_of_schema.get(&t)){
+            None => return Ok(()),
+            Some(s) => s
+        };
+        let errors = validator::validate(schema,&value,call_type);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        return Err(errors);
+    }
+
+    //query variant `balance` is answered by `BalanceResponse`
+    pub fn infer_response_title(variant : &str) -> String{
+        let mut title = String::new();
+        for word in variant.split('_') {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                title.extend(first.to_uppercase());
+                title.push_str(chars.as_str());
+            }
+        }
+        title += "Response";
+        return title;
+    }
+
+    pub fn set_response_type(&mut self,variant : &str,title : &str){
+        self.map_of_response.insert(variant.to_string(),title.to_string());
+    }
+
+    pub fn response_title_of(&self,query_msg : &str) -> Option<String>{
+        let value : serde_json::Value = match serde_json::from_str(query_msg){
+            Ok(v) => v,
+            Err(_e) => return None
+        };
+        let variant = match &value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Object(obj) => match obj.keys().next(){
+                None => return None,
+                Some(k) => k.clone()
+            },
+            _ => return None
+        };
+        if let Some(title) = self.map_of_response.get(&variant) {
+            return Some(title.clone());
+        }
+        let inferred = Analyzer::infer_response_title(variant.as_str());
+        if self.map_of_schema.contains_key(&inferred) {
+            return Some(inferred);
+        }
+        return None;
+    }
+
+    //decode query result and check it against response schema, return pretty json and mismatches
+    pub fn check_query_response(&self,query_msg : &str,data : &[u8]) -> Result<String,Vec<String>>{
+        let value : serde_json::Value = match serde_json::from_slice(data){
+            Ok(v) => v,
+            Err(e) => return Err(vec![format!("response is not json, {}",e)])
+        };
+        let pretty = serde_json::to_string_pretty(&value).unwrap_or_default();
+        let title = match self.response_title_of(query_msg){
+            None => return Ok(pretty),
+            Some(t) => t
+        };
+        let schema = match self.map_of_schema.get(&title){
+            None => return Err(vec![format!("response schema {} not found",title)]),
+            Some(s) => s
+        };
+        let errors = validator::validate(schema,&value,title.as_str());
+        if errors.is_empty() {
+            return Ok(pretty);
+        }
+        return Err(errors);
+    }
+
+    //load every *.json schema file in dir, return count of loaded schema or the reason why nothing loaded
+    pub fn try_load_json_schema(&mut self,dir : &Path) -> Result<usize,String>{
         let all_json_file = match std::fs::read_dir(dir){
-            Err(_e) => return false,
+            Err(e) => return Err(format!("can not read directory, {}",e)),
             Ok(f) => f
         };
+        let mut count = 0;
         for file in all_json_file {
-            self.analyze_schema(file.unwrap().path().display().to_string());
+            let path = match file {
+                Err(_e) => continue,
+                Ok(f) => f.path()
+            };
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            if self.analyze_schema(path.display().to_string()) {
+                count += 1;
+            }
         }
-        return true;
+        if count == 0 {
+            return Err("no json schema file with title found".to_string());
+        }
+        return Ok(count);
     }
 
-    pub fn auto_load_json_schema(&mut self,file_path:&String) -> bool{
-        let seg = match file_path.rfind('/') {
-            None => return false,
-            Some(idx) => idx,
+    fn resolve_path(path : &str) -> PathBuf{
+        let p = PathBuf::from(path);
+        if p.is_absolute() {
+            return p;
+        }
+        return match std::env::current_dir(){
+            Err(_e) => p,
+            Ok(cwd) => cwd.join(p)
         };
-        let (parent_path,_) = file_path.split_at(seg);
-        println!("Auto loading json schema from {}/schema",parent_path);
-        return self.try_load_json_schema(parent_path.to_string() + "/schema");
+    }
+
+    //schema sits beside wasm file, or in the contract crate if wasm was built into `artifacts/` by rust-optimizer
+    pub fn schema_candidates(file_path : &str) -> Vec<PathBuf>{
+        let wasm = Analyzer::resolve_path(file_path);
+        let mut candidates = Vec::new();
+        let parent = match wasm.parent(){
+            None => return candidates,
+            Some(p) => p.to_path_buf()
+        };
+        candidates.push(parent.join("schema"));
+        if parent.file_name().and_then(|n| n.to_str()) != Some("artifacts") {
+            return candidates;
+        }
+        let workspace = match parent.parent(){
+            None => return candidates,
+            Some(w) => w.to_path_buf()
+        };
+        candidates.push(workspace.join("schema"));
+        if let Some(stem) = wasm.file_stem().and_then(|s| s.to_str()) {
+            //rust-optimizer names artifacts by crate name with `_`, and may append the arch like `-aarch64`
+            let name = stem.trim_end_matches("-aarch64");
+            for crate_name in &[name.to_string(),name.replace('_',"-")] {
+                candidates.push(workspace.join("contracts").join(crate_name).join("schema"));
+                candidates.push(workspace.join(crate_name).join("schema"));
+            }
+        }
+        return Analyzer::unique_paths(candidates);
+    }
+
+    //drop paths naming a directory seen before, in any position, so no schema dir is loaded twice
+    fn unique_paths(paths : Vec<PathBuf>) -> Vec<PathBuf>{
+        let mut seen = HashSet::new();
+        return paths.into_iter()
+            .filter(|p| seen.insert(std::fs::canonicalize(p).unwrap_or_else(|_e| p.clone())))
+            .collect();
+    }
+
+    //load schema from every dir in schema_dirs, or search around wasm file if none given
+    pub fn auto_load_json_schema(&mut self,file_path : &str,schema_dirs : &Vec<String>) -> bool{
+        let mut reasons : Vec<String> = Vec::new();
+        let mut loaded = false;
+        if !schema_dirs.is_empty() {
+            let dirs = Analyzer::unique_paths(schema_dirs.iter().map(|d| Analyzer::resolve_path(d.as_str())).collect());
+            for path in dirs {
+                match self.try_load_json_schema(&path) {
+                    Ok(count) => {
+                        watcher::logger_info(&format!("Loaded {} json schema from {}",count,path.display()));
+                        loaded = true;
+                    },
+                    Err(e) => reasons.push(format!("{} : {}",path.display(),e))
+                }
+            }
+        }else {
+            for path in Analyzer::schema_candidates(file_path) {
+                watcher::logger_info(&format!("Auto loading json schema from {}",path.display()));
+                match self.try_load_json_schema(&path) {
+                    Ok(count) => {
+                        watcher::logger_info(&format!("Loaded {} json schema from {}",count,path.display()));
+                        loaded = true;
+                        break;
+                    },
+                    Err(e) => reasons.push(format!("{} : {}",path.display(),e))
+                }
+            }
+        }
+        if !loaded {
+            watcher::logger_info("No json schema loaded:");
+            for r in reasons {
+                watcher::logger_info(&format!("\t{}",r));
+            }
+            watcher::logger_info("use --schema <DIR> to set schema directory");
+        }
+        return loaded;
     }
 }
 
@@ -0,0 +1,109 @@
+//bank module of simulation, balances of every address shared by engine and querier
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use cosmwasm_std::{to_binary, AllBalanceResponse, BalanceResponse, BankQuery, Coin, HumanAddr, QuerierResult, SystemError, Uint128};
+
+#[derive(Default, Clone)]
+pub struct Bank {
+    balances : Rc<RefCell<BTreeMap<HumanAddr,Vec<Coin>>>>,
+}
+
+impl Bank {
+    pub fn new() -> Self{
+        return Bank::default();
+    }
+
+    //replace all balances of address
+    pub fn set_balance(&self,address : &HumanAddr,coins : Vec<Coin>){
+        let coins : Vec<Coin> = coins.into_iter().filter(|c| c.amount.u128() > 0).collect();
+        let mut balances = self.balances.borrow_mut();
+        if coins.is_empty() {
+            balances.remove(address);
+        }else {
+            balances.insert(address.clone(),coins);
+        }
+    }
+
+    pub fn balance(&self,address : &HumanAddr,denom : &str) -> Coin{
+        let amount = match self.balances.borrow().get(address).and_then(|coins| coins.iter().find(|c| c.denom == denom)) {
+            None => Uint128(0),
+            Some(c) => c.amount
+        };
+        return Coin{
+            denom: denom.to_string(),
+            amount
+        };
+    }
+
+    pub fn all_balances(&self,address : &HumanAddr) -> Vec<Coin>{
+        return self.balances.borrow().get(address).cloned().unwrap_or_default();
+    }
+
+    pub fn all(&self) -> BTreeMap<HumanAddr,Vec<Coin>>{
+        return self.balances.borrow().clone();
+    }
+
+    pub fn restore(&self,balances : BTreeMap<HumanAddr,Vec<Coin>>){
+        *self.balances.borrow_mut() = balances;
+    }
+
+    //add coins that come from nowhere, like rewards or starting delegations, a balance stops at u128::MAX
+    pub fn mint(&self,to : &HumanAddr,coin : &Coin){
+        let mut coins = self.all_balances(to);
+        match coins.iter_mut().find(|c| c.denom == coin.denom) {
+            Some(c) => c.amount = Uint128(c.amount.u128().saturating_add(coin.amount.u128())),
+            None => coins.push(coin.clone())
+        }
+        self.set_balance(to,coins);
+    }
+
+    //move coins from one address to another, nothing is moved if any coin is short
+    pub fn transfer(&self,from : &HumanAddr,to : &HumanAddr,coins : &[Coin]) -> Result<(),String>{
+        let mut from_coins = self.all_balances(from);
+        for coin in coins {
+            let amount = coin.amount.u128();
+            match from_coins.iter_mut().find(|c| c.denom == coin.denom) {
+                Some(c) if c.amount.u128() >= amount => c.amount = Uint128(c.amount.u128() - amount),
+                _ => return Err(format!("insufficient funds: {} has {}, needs {}{}",from,self.balance(from,coin.denom.as_str()).amount,amount,coin.denom))
+            }
+        }
+        //sending to itself only needs the funds check, writing both balances would double the coins
+        if from == to {
+            return Ok(());
+        }
+        let mut to_coins = self.all_balances(to);
+        for coin in coins {
+            match to_coins.iter_mut().find(|c| c.denom == coin.denom) {
+                Some(c) => {
+                    c.amount = match c.amount.u128().checked_add(coin.amount.u128()) {
+                        None => return Err(format!("balance overflow: {} can not hold more {}",to,coin.denom)),
+                        Some(a) => Uint128(a)
+                    };
+                },
+                None => to_coins.push(coin.clone())
+            }
+        }
+        self.set_balance(from,from_coins);
+        self.set_balance(to,to_coins);
+        return Ok(());
+    }
+
+    pub fn query(&self,request : &BankQuery) -> QuerierResult{
+        let bin = match request {
+            BankQuery::Balance{address,denom} => to_binary(&BalanceResponse{
+                amount: self.balance(address,denom.as_str())
+            }),
+            BankQuery::AllBalances{address} => to_binary(&AllBalanceResponse{
+                amount: self.all_balances(address)
+            })
+        };
+        return match bin {
+            Ok(b) => Ok(Ok(b)),
+            Err(e) => Err(SystemError::InvalidResponse{
+                error: e.to_string()
+            })
+        };
+    }
+}
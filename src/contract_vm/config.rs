@@ -0,0 +1,219 @@
+//project config file with defaults of simulation, values use the same format as command line flags
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::Coin;
+use crate::contract_vm::{analyzer, accounts, watcher};
+use crate::contract_vm::settings::Settings;
+
+pub static DEFAULT_CONFIG_FILE: &str = "simulate.toml";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_prefix : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_length : Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_height : Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_time : Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_address : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_limit : Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub funds : Option<String>,
+    //directories of json schema files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema : Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response : Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account : Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance : Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validator : Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegation : Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bonded_denom : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward_rate : Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_fixture : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_process : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_fixture : Option<String>,
+}
+
+//paths in config are relative to directory of config file
+fn relative_to(base : &Path,path : &str) -> String{
+    if Path::new(path).is_absolute() || base.as_os_str().is_empty() {
+        return path.to_string();
+    }
+    return base.join(path).to_string_lossy().to_string();
+}
+
+pub fn load_config(file : &str) -> Result<Config,String>{
+    if !Path::new(file).is_file() {
+        return Err(format!("config {} not found",file));
+    }
+    let data = analyzer::load_data_from_file(file)?;
+    let text = String::from_utf8_lossy(data.as_slice());
+    let mut config : Config = match toml::from_str(&text){
+        Err(e) => return Err(format!("config {} is not valid, {}",file,e)),
+        Ok(c) => c
+    };
+    config.resolve_paths(Path::new(file).parent().unwrap_or_else(|| Path::new("")));
+    return Ok(config);
+}
+
+//config given by flag must exist, simulate.toml of working directory is used if found
+pub fn find_config(file : Option<&str>) -> Result<Option<Config>,String>{
+    return match file {
+        Some(f) => Ok(Some(load_config(f)?)),
+        None => {
+            if !Path::new(DEFAULT_CONFIG_FILE).is_file() {
+                return Ok(None);
+            }
+            watcher::logger_info(&format!("using config {}",DEFAULT_CONFIG_FILE));
+            Ok(Some(load_config(DEFAULT_CONFIG_FILE)?))
+        }
+    };
+}
+
+//alias, address and coins written like `--account`
+fn account_def(alias : &str,address : &Option<String>,coins : &[Coin]) -> String{
+    let mut def = alias.to_string();
+    if let Some(a) = address {
+        def = format!("{}={}",def,a);
+    }
+    if !coins.is_empty() {
+        def = format!("{}:{}",def,accounts::format_coins(coins));
+    }
+    return def;
+}
+
+fn non_empty(items : Vec<String>) -> Option<Vec<String>>{
+    return if items.is_empty() { None } else { Some(items) };
+}
+
+impl Config {
+    //config with every value of settings, fixture files become absolute so it works from any directory
+    pub fn from_settings(settings : &Settings) -> Config{
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let absolute = |file : &Option<String>| file.as_ref().map(|f| relative_to(cwd.as_path(),f.as_str()));
+        return Config{
+            address_prefix: settings.address_prefix.clone(),
+            canonical_length: Some(settings.canonical_length),
+            chain_id: Some(settings.chain_id.clone()),
+            block_height: Some(settings.block_height),
+            block_time: Some(settings.block_time),
+            contract_address: settings.contract_address.clone(),
+            gas_limit: Some(settings.gas_limit),
+            sender: settings.sender.clone(),
+            funds: if settings.funds.is_empty() { None } else { Some(accounts::format_coins(&settings.funds)) },
+            schema: None,
+            response: None,
+            account: non_empty(settings.accounts.iter().map(|(alias,address,coins)| account_def(alias,address,coins)).collect()),
+            balance: non_empty(settings.balances.iter().map(|(who,coins)| format!("{}={}",who,accounts::format_coins(coins))).collect()),
+            validator: non_empty(settings.validators.iter().map(|(name,commission)| format!("{}:{}",name,commission)).collect()),
+            delegation: non_empty(settings.delegations.iter().map(|(delegator,validator,amount)| format!("{}:{}:{}",delegator,validator,amount)).collect()),
+            bonded_denom: settings.bonded_denom.clone(),
+            reward_rate: settings.reward_rate,
+            custom_fixture: absolute(&settings.custom_fixture),
+            custom_process: settings.custom_process.clone(),
+            contract_fixture: absolute(&settings.contract_fixture)
+        };
+    }
+
+    //paths in config are relative to directory base of the file holding it
+    pub fn resolve_paths(&mut self,base : &Path){
+        if let Some(dirs) = &mut self.schema {
+            for dir in dirs.iter_mut() {
+                *dir = relative_to(base,dir.as_str());
+            }
+        }
+        for file in vec![&mut self.custom_fixture,&mut self.contract_fixture] {
+            if let Some(f) = file {
+                *f = relative_to(base,f.as_str());
+            }
+        }
+    }
+
+    //set settings from config, command line is applied afterwards and overrides it
+    pub fn apply(&self,settings : &mut Settings) -> Result<(),String>{
+        if self.custom_fixture.is_some() && self.custom_process.is_some() {
+            return Err("custom_fixture and custom_process of config can not be used together".to_string());
+        }
+        if let Some(prefix) = &self.address_prefix {
+            settings.address_prefix = Some(prefix.clone());
+        }
+        if let Some(len) = self.canonical_length {
+            if len == 0 {
+                return Err("canonical_length of config must be more than 0".to_string());
+            }
+            settings.canonical_length = len;
+        }
+        if let Some(chain_id) = &self.chain_id {
+            settings.chain_id = chain_id.clone();
+        }
+        if let Some(height) = self.block_height {
+            settings.block_height = height;
+        }
+        if let Some(time) = self.block_time {
+            settings.block_time = time;
+        }
+        if let Some(address) = &self.contract_address {
+            settings.contract_address = Some(address.clone());
+        }
+        if let Some(gas) = self.gas_limit {
+            if gas == 0 {
+                return Err("gas_limit of config must be more than 0".to_string());
+            }
+            settings.gas_limit = gas;
+        }
+        if let Some(sender) = &self.sender {
+            settings.sender = Some(sender.clone());
+        }
+        if let Some(funds) = &self.funds {
+            settings.funds = accounts::parse_coins(funds.as_str())?;
+        }
+        for def in self.account.iter().flatten() {
+            settings.add_account(def.as_str())?;
+        }
+        for def in self.balance.iter().flatten() {
+            settings.add_balance(def.as_str())?;
+        }
+        for def in self.validator.iter().flatten() {
+            settings.add_validator(def.as_str())?;
+        }
+        for def in self.delegation.iter().flatten() {
+            settings.add_delegation(def.as_str())?;
+        }
+        if let Some(denom) = &self.bonded_denom {
+            settings.bonded_denom = Some(denom.clone());
+        }
+        if let Some(rate) = self.reward_rate {
+            settings.reward_rate = Some(rate);
+        }
+        if let Some(file) = &self.custom_fixture {
+            settings.custom_fixture = Some(file.clone());
+        }
+        if let Some(command) = &self.custom_process {
+            settings.custom_process = Some(command.clone());
+        }
+        if let Some(file) = &self.contract_fixture {
+            settings.contract_fixture = Some(file.clone());
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,109 @@
+//line editing of interactive session, with history kept between sessions and completion of known words
+
+use std::cell::RefCell;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Config, Context, Editor, Helper, OutputStreamType};
+use crate::contract_vm::watcher;
+
+static HISTORY_FILE: &str = ".cosmwasm_simulate_history";
+static PROMPT: &str = "> ";
+
+//completes the word under cursor from call types, message names, field names, aliases and commands
+struct WordCompleter {
+    words : Vec<String>,
+}
+
+impl Completer for WordCompleter {
+    type Candidate = Pair;
+
+    fn complete(&self,line : &str,pos : usize,_ctx : &Context<'_>) -> rustyline::Result<(usize,Vec<Pair>)>{
+        //json punctuation separates words too, so fields can be completed inside raw json
+        let start = line[..pos].char_indices().rev()
+            .find(|(_,c)| c.is_whitespace() || "{}[]\",:'".contains(*c))
+            .map(|(i,c)| i + c.len_utf8())
+            .unwrap_or(0);
+        let prefix = &line[start..pos];
+        let candidates = self.words.iter()
+            .filter(|w| w.starts_with(prefix))
+            .map(|w| Pair{ display: w.clone(), replacement: w.clone() })
+            .collect();
+        return Ok((start,candidates));
+    }
+}
+
+impl Hinter for WordCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for WordCompleter {}
+
+impl Validator for WordCompleter {}
+
+impl Helper for WordCompleter {}
+
+thread_local! {
+    static EDITOR: RefCell<Option<Editor<WordCompleter>>> = RefCell::new(None);
+}
+
+fn history_path() -> String{
+    return match std::env::var("HOME") {
+        Ok(home) => format!("{}/{}",home,HISTORY_FILE),
+        Err(_e) => HISTORY_FILE.to_string()
+    };
+}
+
+//start line editing with history of earlier sessions
+pub fn start(words : Vec<String>){
+    //prompt goes to stderr in json mode, stdout only carries events
+    let stream = if watcher::is_json_output() { OutputStreamType::Stderr } else { OutputStreamType::Stdout };
+    let mut editor = Editor::<WordCompleter>::with_config(Config::builder().output_stream(stream).build());
+    //no history yet on first run
+    let _ = editor.load_history(history_path().as_str());
+    EDITOR.with(|e| *e.borrow_mut() = Some(editor));
+    set_words(words);
+}
+
+//replace completion words, like after schema is loaded
+pub fn set_words(mut words : Vec<String>){
+    words.sort();
+    words.dedup();
+    EDITOR.with(|e| {
+        if let Some(editor) = &mut *e.borrow_mut() {
+            editor.set_helper(Some(WordCompleter{ words }));
+        }
+    });
+}
+
+//read one line, None on end of input
+pub fn read_line() -> Option<String>{
+    return EDITOR.with(|e| {
+        let mut cell = e.borrow_mut();
+        let editor = match &mut *cell {
+            None => {
+                //line editing not started, plain stdin
+                let mut line = String::new();
+                return match std::io::stdin().read_line(&mut line) {
+                    Ok(0) | Err(_) => None,
+                    Ok(_) => Some(line.trim_end_matches(|c| c == '\n' || c == '\r').to_string())
+                };
+            },
+            Some(editor) => editor
+        };
+        match editor.readline(PROMPT) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    editor.add_history_entry(line.as_str());
+                    let _ = editor.save_history(history_path().as_str());
+                }
+                Some(line)
+            },
+            //ctrl-c drops the line being typed
+            Err(ReadlineError::Interrupted) => Some(String::new()),
+            Err(_) => None
+        }
+    });
+}
@@ -0,0 +1,154 @@
+//fixture of contracts that are not loaded, answers wasm smart and raw queries sent to them
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use cosmwasm_std::{Binary, HumanAddr, QuerierResult, StdError, SystemError, WasmQuery};
+use serde_json::Value;
+use crate::contract_vm::{accounts, analyzer, expect, watcher};
+
+struct SmartEntry {
+    contract : HumanAddr,
+    query : Value,
+    response : Result<Value,String>,
+}
+
+struct RawEntry {
+    contract : HumanAddr,
+    key : Vec<u8>,
+    value : Vec<u8>,
+}
+
+#[derive(Default)]
+struct Fixture {
+    smart : Vec<SmartEntry>,
+    raw : Vec<RawEntry>,
+}
+
+//shared by querier and engine, entries are loaded after accounts so contracts can be named by `@alias`
+#[derive(Clone, Default)]
+pub struct Contracts {
+    fixture : Rc<RefCell<Fixture>>,
+    accounts : accounts::Accounts,
+}
+
+fn field<'a>(item : &'a Value,idx : usize,name : &str) -> Result<&'a Value,String>{
+    return match item.get(name) {
+        None => Err(format!("contract fixture: entry {} has no {}",idx,name)),
+        Some(v) => Ok(v)
+    };
+}
+
+//`@alias` inside fixture json is expanded like in messages
+fn expand(accounts : &accounts::Accounts,value : &Value) -> Result<Value,String>{
+    let text = accounts.expand(value.to_string().as_str())?;
+    return match serde_json::from_str(text.as_str()){
+        Err(e) => Err(e.to_string()),
+        Ok(v) => Ok(v)
+    };
+}
+
+impl Contracts {
+    pub fn new(accounts : accounts::Accounts) -> Self{
+        return Contracts{
+            fixture: Rc::default(),
+            accounts
+        };
+    }
+
+    //fixture is an array of entries like
+    //{"contract":"@oracle","query":{..},"response":{..}} (or "error":"..") for smart queries
+    //{"contract":"@oracle","key":"config","value":{..}} for raw queries, value is stored as json
+    //key is written like storage keys of scenarios, `ns:a/key` or `hex:..` for binary keys
+    pub fn load(&self,file : &str,accounts : &accounts::Accounts) -> Result<(),String>{
+        let data = analyzer::load_data_from_file(file)?;
+        let root : Value = match serde_json::from_slice(data.as_slice()){
+            Err(e) => return Err(format!("contract fixture {} is not json, {}",file,e)),
+            Ok(v) => v
+        };
+        let items = match root.as_array() {
+            None => return Err(format!("contract fixture {} must be an array",file)),
+            Some(items) => items
+        };
+        let mut fixture = self.fixture.borrow_mut();
+        for (idx,item) in items.iter().enumerate() {
+            let contract = match field(item,idx,"contract")?.as_str() {
+                None => return Err(format!("contract fixture: contract of entry {} must be a string",idx)),
+                Some(c) => accounts.resolve(c)
+            };
+            if let Some(query) = item.get("query") {
+                let response = match item.get("error") {
+                    Some(e) => Err(e.as_str().map(|s| s.to_string()).unwrap_or_else(|| e.to_string())),
+                    None => Ok(expand(accounts,field(item,idx,"response")?)?)
+                };
+                fixture.smart.push(SmartEntry{
+                    contract,
+                    query: expand(accounts,query)?,
+                    response
+                });
+            }else {
+                let key = match field(item,idx,"key")?.as_str() {
+                    None => return Err(format!("contract fixture: key of entry {} must be a string",idx)),
+                    Some(k) => match expect::storage_key(k) {
+                        Err(e) => return Err(format!("contract fixture: key of entry {} is not valid, {}",idx,e)),
+                        Ok(k) => k
+                    }
+                };
+                let value = expand(accounts,field(item,idx,"value")?)?.to_string().into_bytes();
+                fixture.raw.push(RawEntry{
+                    contract,
+                    key,
+                    value
+                });
+            }
+        }
+        watcher::logger_info(&format!("contract fixture {} loaded, {} smart {} raw",file,fixture.smart.len(),fixture.raw.len()));
+        return Ok(());
+    }
+
+    fn knows(&self,contract : &HumanAddr) -> bool{
+        let fixture = self.fixture.borrow();
+        return fixture.smart.iter().any(|e| e.contract == *contract) || fixture.raw.iter().any(|e| e.contract == *contract);
+    }
+
+    pub fn query(&self,request : &WasmQuery) -> QuerierResult{
+        match request {
+            WasmQuery::Smart{contract_addr,msg} => {
+                let query : Value = match serde_json::from_slice(msg.as_slice()){
+                    Err(e) => return Err(SystemError::InvalidRequest{
+                        error: format!("smart query to {} is not json, {}",contract_addr,e)
+                    }),
+                    Ok(v) => v
+                };
+                let fixture = self.fixture.borrow();
+                if let Some(e) = fixture.smart.iter().find(|e| e.contract == *contract_addr && e.query == query) {
+                    return match &e.response {
+                        Err(err) => Ok(Err(StdError::generic_err(err.clone()))),
+                        Ok(v) => Ok(Ok(Binary(v.to_string().into_bytes())))
+                    };
+                }
+                watcher::logger_unmatched_query(&self.accounts,contract_addr.as_str(),query.to_string().as_str());
+            },
+            WasmQuery::Raw{contract_addr,key} => {
+                let fixture = self.fixture.borrow();
+                if let Some(e) = fixture.raw.iter().find(|e| e.contract == *contract_addr && e.key.as_slice() == key.as_slice()) {
+                    return Ok(Ok(Binary(e.value.clone())));
+                }
+                watcher::logger_unmatched_query(&self.accounts,contract_addr.as_str(),format!("raw key {}",String::from_utf8_lossy(key.as_slice())).as_str());
+                //like a chain, a missing key of a known contract reads as empty
+                if self.knows(contract_addr) {
+                    return Ok(Ok(Binary(Vec::new())));
+                }
+            }
+        }
+        let contract_addr = match request {
+            WasmQuery::Smart{contract_addr,..} => contract_addr,
+            WasmQuery::Raw{contract_addr,..} => contract_addr
+        };
+        if self.knows(contract_addr) {
+            return Ok(Err(StdError::generic_err(format!("query not in fixture of contract {}",contract_addr))));
+        }
+        return Err(SystemError::NoSuchContract{
+            addr: contract_addr.clone()
+        });
+    }
+}
@@ -0,0 +1,244 @@
+//custom queries and messages of chain specific modules, answered by a json fixture or an external process
+
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use cosmwasm_std::{Binary, CustomQuery, HumanAddr, QuerierResult, StdError, SystemError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::contract_vm::{analyzer, watcher};
+
+//any json is accepted as custom query or message, the handler decides what it means
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(transparent)]
+pub struct CustomMsg(pub Value);
+
+impl CustomQuery for CustomMsg {}
+
+//a process that does not reply in time is stopped, so a hung handler can not block the simulation
+static PROCESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct FixtureEntry {
+    request : Value,
+    //response of query, ignored for messages
+    response : Value,
+    error : Option<String>,
+}
+
+enum Backend {
+    Unsupported,
+    Fixture {
+        queries : Vec<FixtureEntry>,
+        messages : Vec<FixtureEntry>,
+    },
+    Process {
+        command : String,
+        child : Child,
+        stdin : ChildStdin,
+        //lines of stdout, read by a thread so waiting can time out
+        replies : Receiver<std::io::Result<String>>,
+    },
+}
+
+//shared by querier and engine, so the same process serves queries and messages
+#[derive(Clone)]
+pub struct CustomHandler {
+    backend : Rc<RefCell<Backend>>,
+}
+
+fn parse_entries(root : &Value,key : &str,request_key : &str) -> Result<Vec<FixtureEntry>,String>{
+    let items = match root.get(key) {
+        None => return Ok(Vec::new()),
+        Some(Value::Array(items)) => items,
+        Some(_) => return Err(format!("custom fixture: {} must be an array",key))
+    };
+    let mut entries = Vec::new();
+    for (idx,item) in items.iter().enumerate() {
+        let request = match item.get(request_key) {
+            None => return Err(format!("custom fixture: {}[{}] has no {}",key,idx,request_key)),
+            Some(r) => r.clone()
+        };
+        entries.push(FixtureEntry{
+            request,
+            response: item.get("response").cloned().unwrap_or(Value::Null),
+            error: item.get("error").and_then(|e| e.as_str()).map(|e| e.to_string())
+        });
+    }
+    return Ok(entries);
+}
+
+//exit code of handler process, waits shortly since output may close just before exit
+fn exit_status(child : &mut Child) -> String{
+    for _ in 0..10 {
+        match child.try_wait() {
+            Ok(Some(status)) => return format!("exited with {}",status),
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => return format!("has unknown state, {}",e)
+        }
+    }
+    return "closed its output".to_string();
+}
+
+impl CustomHandler {
+    pub fn default() -> Self{
+        return CustomHandler{
+            backend: Rc::new(RefCell::new(Backend::Unsupported))
+        };
+    }
+
+    //fixture like {"queries":[{"request":{..},"response":{..}}],"messages":[{"msg":{..}},{"msg":{..},"error":".."}]}
+    pub fn from_fixture(file : &str) -> Result<Self,String>{
+        let data = analyzer::load_data_from_file(file)?;
+        let root : Value = match serde_json::from_slice(data.as_slice()){
+            Err(e) => return Err(format!("custom fixture {} is not json, {}",file,e)),
+            Ok(v) => v
+        };
+        let queries = parse_entries(&root,"queries","request")?;
+        let messages = parse_entries(&root,"messages","msg")?;
+        watcher::logger_info(&format!("custom fixture {} loaded, {} queries {} messages",file,queries.len(),messages.len()));
+        return Ok(CustomHandler{
+            backend: Rc::new(RefCell::new(Backend::Fixture{ queries, messages }))
+        });
+    }
+
+    //external process reads one json request per line from stdin and writes one json reply per line to stdout
+    pub fn from_process(command : &str) -> Result<Self,String>{
+        let mut child = match Command::new("sh").arg("-c").arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn(){
+            Err(e) => return Err(format!("can not start custom handler [{}], {}",command,e)),
+            Ok(c) => c
+        };
+        let stdin = child.stdin.take().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender,replies) = mpsc::channel();
+        std::thread::spawn(move || {
+            loop {
+                let mut line = String::new();
+                match stdout.read_line(&mut line) {
+                    //end of output closes the channel
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if sender.send(Ok(line)).is_err() {
+                            break;
+                        }
+                    },
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                        break;
+                    }
+                }
+            }
+        });
+        return Ok(CustomHandler{
+            backend: Rc::new(RefCell::new(Backend::Process{
+                command: command.to_string(),
+                child,
+                stdin,
+                replies
+            }))
+        });
+    }
+
+    //send request line and read reply line like {"ok":..} or {"error":".."}
+    fn ask_process(&self,request : &Value) -> Result<Result<Value,String>,String>{
+        let mut backend = self.backend.borrow_mut();
+        let (command,child,stdin,replies) = match &mut *backend {
+            Backend::Process{command,child,stdin,replies} => (command,child,stdin,replies),
+            _ => return Err("custom handler is not a process".to_string())
+        };
+        if let Err(e) = writeln!(stdin,"{}",request).and_then(|_| stdin.flush()) {
+            return Err(format!("custom handler [{}] can not be written, {}, {}",command,e,exit_status(child)));
+        }
+        let line = match replies.recv_timeout(PROCESS_TIMEOUT) {
+            Ok(Ok(l)) => l,
+            Ok(Err(e)) => return Err(format!("custom handler [{}] can not be read, {}",command,e)),
+            Err(RecvTimeoutError::Timeout) => {
+                //a late reply would answer the next request, so the process is stopped
+                let _ = child.kill();
+                return Err(format!("custom handler [{}] did not reply in {} seconds and was stopped",command,PROCESS_TIMEOUT.as_secs()));
+            },
+            Err(RecvTimeoutError::Disconnected) => return Err(format!("custom handler [{}] {}",command,exit_status(child)))
+        };
+        let reply : Value = match serde_json::from_str(line.trim()){
+            Err(e) => return Err(format!("custom handler [{}] replied no json [{}], {}",command,line.trim(),e)),
+            Ok(v) => v
+        };
+        if let Some(e) = reply.get("error") {
+            return Ok(Err(match e.as_str() {
+                None => e.to_string(),
+                Some(s) => s.to_string()
+            }));
+        }
+        return match reply.get("ok") {
+            None => Err(format!("custom handler [{}] replied neither ok nor error [{}]",command,line.trim())),
+            Some(v) => Ok(Ok(v.clone()))
+        };
+    }
+
+    pub fn query(&self,request : &CustomMsg) -> QuerierResult{
+        let reply = if self.is_process() {
+            match self.ask_process(&serde_json::json!({"query": request.0})) {
+                Err(e) => return Err(SystemError::InvalidResponse{ error: e }),
+                Ok(r) => r
+            }
+        }else {
+            match &*self.backend.borrow() {
+                Backend::Fixture{queries,..} => match queries.iter().find(|q| q.request == request.0) {
+                    None => return Err(SystemError::UnsupportedRequest{
+                        kind: format!("custom query {} not in fixture",request.0)
+                    }),
+                    Some(q) => match &q.error {
+                        Some(e) => Err(e.clone()),
+                        None => Ok(q.response.clone())
+                    }
+                },
+                _ => return Err(SystemError::UnsupportedRequest{
+                    kind: format!("custom query {}, set --custom-fixture or --custom-process",request.0)
+                })
+            }
+        };
+        return match reply {
+            Err(e) => Ok(Err(StdError::generic_err(e))),
+            Ok(v) => Ok(Ok(Binary(v.to_string().into_bytes())))
+        };
+    }
+
+    fn is_process(&self) -> bool{
+        return match &*self.backend.borrow() {
+            Backend::Process{..} => true,
+            _ => false
+        };
+    }
+
+    //execute custom message sent by contract, error reverts the call
+    pub fn execute(&self,sender : &HumanAddr,msg : &CustomMsg) -> Result<(),String>{
+        if self.is_process() {
+            return self.ask_process(&serde_json::json!({"msg": msg.0, "sender": sender}))?.map(|_| ());
+        }
+        return match &*self.backend.borrow() {
+            Backend::Fixture{messages,..} => match messages.iter().find(|m| m.request == msg.0) {
+                None => Err(format!("custom message {} not in fixture",msg.0)),
+                Some(m) => match &m.error {
+                    Some(e) => Err(e.clone()),
+                    None => Ok(())
+                }
+            },
+            _ => Err(format!("custom message {} not supported, set --custom-fixture or --custom-process",msg.0))
+        };
+    }
+}
+
+impl Drop for Backend {
+    fn drop(&mut self){
+        if let Backend::Process{child,..} = self {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
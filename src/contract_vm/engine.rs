@@ -2,7 +2,7 @@
 extern crate cosmwasm_vm;
 extern crate cosmwasm_std;
 extern crate serde_json;
-use std::fmt::Write;
+use std::fmt;
 use wasmer_runtime_core::{
     backend::Compiler,
     codegen::{MiddlewareChain, StreamingCompiler},
@@ -11,21 +11,80 @@ use wasmer_runtime_core::{
 use wasmer_middleware_common::metering;
 
 use wasmer_singlepass_backend::ModuleCodeGenerator as SinglePassMCG;
-use self::cosmwasm_vm::{Instance};
-use self::cosmwasm_vm::testing::{ MockQuerier};
-use self::cosmwasm_std::{Uint128, Binary};
-use crate::contract_vm::{mock, analyzer};
+use self::cosmwasm_vm::{Instance, Api, VmError};
+use std::collections::BTreeMap;
+use self::cosmwasm_std::{Binary, HumanAddr, CosmosMsg, BankMsg, Coin, LogAttribute};
+use crate::contract_vm::{mock, analyzer, settings, accounts, bank, staking, custom, contracts, scenario, watcher};
 
-static DEFAULT_GAS_LIMIT: u64 = 500_000;
+pub static DEFAULT_GAS_LIMIT: u64 = 500_000;
+static DEFAULT_SENDER: &str = "okchain_kamid";
+static DEFAULT_CONTRACT: &str = "fake_contract_addr";
+pub static DEFAULT_CHAIN_ID: &str = "okchain";
+static SECONDS_PER_BLOCK: u64 = 5;
 static COMPILE_GAS_LIMIT: u64 = 10_000_000_000;
+static UNDO_LIMIT: usize = 100;
 
 
+//everything a call can change, used to reset or undo a session
+#[derive(Clone, PartialEq)]
+pub struct ChainState {
+    pub storage : BTreeMap<Vec<u8>,Vec<u8>>,
+    pub balances : BTreeMap<HumanAddr,Vec<Coin>>,
+    pub staking : staking::StakingState,
+    pub block : cosmwasm_std::BlockInfo,
+}
+
+pub struct CallResult {
+    pub data : Vec<u8>,
+    pub gas_used : u64,
+    pub messages : Vec<CosmosMsg<custom::CustomMsg>>,
+    pub log : Vec<LogAttribute>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    //rejected by contract, like ParseErr or Unauthorized
+    Contract(String),
+    //raised by vm, like wasm trap or panic
+    Vm(String),
+    //gas limit of call used up, in wasm code or in a call of an imported function
+    OutOfGas(String),
+    Dispatch(String),
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CallError::Contract(e) => write!(f, "contract error: {}", e),
+            CallError::Vm(e) => write!(f, "vm error: {}", e),
+            CallError::OutOfGas(e) => write!(f, "out of gas: {}", e),
+            CallError::Dispatch(e) => write!(f, "{}", e),
+        }
+    }
+}
+
 pub struct ContractInstance {
     pub module : Module,
-    pub instance : Instance<mock::MockStorage,mock::MockApi,MockQuerier>,
+    pub instance : Instance<mock::MockStorage,mock::MockApi,mock::MockQuerier>,
+    pub storage : mock::MockStorage,
+    pub bank : bank::Bank,
+    pub staking : staking::Staking,
+    pub custom : custom::CustomHandler,
+    //set when the session is recorded as scenario
+    pub recorder : Option<scenario::Recorder>,
+    //gas limit of every call
+    pub gas_limit : u64,
+    pub api : mock::MockApi,
     pub wasm_file : String,
     pub env : cosmwasm_std::Env,
+    pub contract_addr : HumanAddr,
+    pub sender : HumanAddr,
+    pub accounts : accounts::Accounts,
     pub analyzer : analyzer::Analyzer,
+    //state right after setup, restored by reset
+    pub initial_state : Option<ChainState>,
+    //state before each successful init and handle with number of steps recorded by then, latest last
+    pub undo_stack : Vec<(ChainState,usize)>,
 }
 
 fn compiler() -> Box<dyn Compiler> {
@@ -41,56 +100,200 @@ fn compiler() -> Box<dyn Compiler> {
 
 impl ContractInstance
 {
-    pub fn new_instance(wasm_file: &str) -> Result<Self,String>{
-        let deps = mock::new_mock(20,&[],"fake_contract_addr");
+    pub fn new_instance(wasm_file: &str,settings: &settings::Settings) -> Result<Self,String>{
+        let api = settings.build_api()?;
+        let contract_addr = match &settings.contract_address {
+            None => ContractInstance::default_address(&api,DEFAULT_CONTRACT),
+            //checked with the env of instance
+            Some(address) => HumanAddr::from(address.as_str())
+        };
+        let bank = bank::Bank::new();
+        let staking = staking::Staking::new();
+        let custom = match (&settings.custom_fixture,&settings.custom_process) {
+            (Some(file),_) => custom::CustomHandler::from_fixture(file.as_str())?,
+            (None,Some(command)) => custom::CustomHandler::from_process(command.as_str())?,
+            (None,None) => custom::CustomHandler::default()
+        };
+        let accounts = accounts::Accounts::new();
+        let contracts = contracts::Contracts::new(accounts.clone());
+        let deps = mock::new_mock(api,bank.clone(),staking.clone(),custom.clone(),contracts.clone(),accounts.clone());
+        let storage = deps.storage.clone();
         let wasm = match analyzer::load_data_from_file(wasm_file){
             Err(e) => return Err(e),
             Ok(code) => code,
         };
-        println!("Compiling code");
+        watcher::logger_info("Compiling code");
         let md = wasmer_runtime_core::compile_with(wasm.as_slice(),compiler().as_ref()).unwrap();
-        let inst = match cosmwasm_vm::Instance::from_code(wasm.as_slice(),deps,DEFAULT_GAS_LIMIT){
-            Err(e) => {
-                println!("cosmwasm_vm::Instance::from_code return error {}",e);
-                return Err("Instance from code execute failed!".to_string());
-            },
+        let inst = match cosmwasm_vm::Instance::from_code(wasm.as_slice(),deps,settings.gas_limit){
+            Err(e) => return Err(format!("Instance from code execute failed, {}",e)),
             Ok(i) => i
         };
-        return Ok(ContractInstance::make_instance(md, inst, wasm_file.to_string()));
+        let mut engine = ContractInstance::make_instance(md, inst, storage, bank, staking, custom, accounts, api, contract_addr, wasm_file.to_string(), settings)?;
+        for (alias,address,balance) in &settings.accounts {
+            let human = match address {
+                None => api.test_address(alias.as_str()),
+                Some(a) => HumanAddr::from(a.as_str())
+            };
+            if let Err(e) = api.canonical_address(&human) {
+                return Err(format!("address {} of account {} is invalid, {}",human,alias,e));
+            }
+            engine.bank.set_balance(&human,balance.clone());
+            engine.accounts.add(alias.as_str(),human,balance.clone());
+        }
+        if let Some(file) = &settings.contract_fixture {
+            contracts.load(file.as_str(),&engine.accounts)?;
+        }
+        for (name,coins) in &settings.balances {
+            let human = engine.accounts.resolve(name.as_str());
+            engine.bank.set_balance(&human,coins.clone());
+        }
+        if let Some(sender) = &settings.sender {
+            engine.set_sender(sender.as_str())?;
+        }
+        engine.env.message.sent_funds = settings.funds.clone();
+        engine.gas_limit = settings.gas_limit;
+        if let Some(denom) = &settings.bonded_denom {
+            engine.staking.set_bonded_denom(denom.as_str());
+        }
+        if let Some(rate) = settings.reward_rate {
+            engine.staking.set_reward_rate(rate);
+        }
+        for (validator,commission) in &settings.validators {
+            let human = engine.accounts.resolve(validator.as_str());
+            engine.staking.add_validator(&human,*commission)?;
+        }
+        for (delegator,validator,amount) in &settings.delegations {
+            let delegator = engine.accounts.resolve(delegator.as_str());
+            let validator = engine.accounts.resolve(validator.as_str());
+            engine.staking.add_delegation(&engine.bank,&delegator,&validator,*amount)?;
+        }
+        engine.initial_state = Some(engine.save_state());
+        return Ok(engine);
     }
 
-    fn make_instance(md : Module,inst : cosmwasm_vm::Instance<mock::MockStorage,mock::MockApi,MockQuerier>,file:String) -> ContractInstance {
+    //copy of storage, balances, staking and block
+    pub fn save_state(&self) -> ChainState{
+        return ChainState{
+            storage: self.storage.snapshot(),
+            balances: self.bank.all(),
+            staking: self.staking.snapshot(),
+            block: self.env.block.clone()
+        };
+    }
+
+    //put back state taken by save_state
+    pub fn load_state(&mut self,state : ChainState){
+        self.storage.restore(state.storage);
+        self.bank.restore(state.balances);
+        self.staking.restore(state.staking);
+        self.env.block = state.block;
+    }
+
+    //back to the state right after setup, like a fresh instance
+    pub fn reset(&mut self){
+        if let Some(state) = self.initial_state.clone() {
+            self.load_state(state);
+        }
+        self.undo_stack.clear();
+        //recorded steps are gone too, so replay matches the session
+        if let Some(recorder) = &mut self.recorder {
+            recorder.truncate(0);
+        }
+    }
+
+    //back to the state before the latest init or handle, false if nothing to undo
+    pub fn undo(&mut self) -> bool{
+        return match self.undo_stack.pop() {
+            None => false,
+            Some((state,recorded)) => {
+                self.load_state(state);
+                //the call and steps recorded after it are undone as well
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.truncate(recorded);
+                }
+                true
+            }
+        };
+    }
+
+    //set sender of following calls by account alias or address
+    pub fn set_sender(&mut self,name : &str) -> Result<HumanAddr,String>{
+        let human = self.accounts.resolve(name);
+        let canonical = match self.api.canonical_address(&human){
+            Err(e) => return Err(format!("sender {} is invalid, {}",name,e)),
+            Ok(c) => c
+        };
+        self.env.message.sender = canonical;
+        self.sender = human.clone();
+        return Ok(human);
+    }
 
-        return ContractInstance {
+    fn make_instance(md : Module,inst : cosmwasm_vm::Instance<mock::MockStorage,mock::MockApi,mock::MockQuerier>,storage : mock::MockStorage,bank : bank::Bank,staking : staking::Staking,custom : custom::CustomHandler,accounts : accounts::Accounts,api : mock::MockApi,contract_addr : HumanAddr,file:String,settings : &settings::Settings) -> Result<ContractInstance,String> {
+        let env = ContractInstance::build_mock_env(&api,&contract_addr,settings)?;
+        return Ok(ContractInstance {
             module:md,
             instance:inst,
+            storage,
+            bank,
+            staking,
+            custom,
+            recorder: None,
+            gas_limit: DEFAULT_GAS_LIMIT,
+            api,
             wasm_file:file,
-            env: ContractInstance::build_mock_env(),
-            analyzer: analyzer::Analyzer::default()
+            env,
+            sender: ContractInstance::default_address(&api,DEFAULT_SENDER),
+            accounts,
+            contract_addr,
+            analyzer: analyzer::Analyzer::default(),
+            initial_state: None,
+            undo_stack: Vec::new()
+        });
+    }
+
+    //raw name if api accepts it, otherwise a valid test address generated from name
+    fn default_address(api : &mock::MockApi,name : &str) -> HumanAddr{
+        let human = HumanAddr::from(name);
+        if api.canonical_address(&human).is_ok() {
+            return human;
         }
+        return api.test_address(name);
     }
 
-    fn build_mock_env() -> cosmwasm_std::Env{
-        return cosmwasm_std::Env{
+    fn build_mock_env(api : &mock::MockApi,contract_addr : &HumanAddr,settings : &settings::Settings) -> Result<cosmwasm_std::Env,String>{
+        let sender = ContractInstance::default_address(api,DEFAULT_SENDER);
+        let sender_canonical = match api.canonical_address(&sender){
+            Err(e) => return Err(format!("sender {} is invalid, {}",sender,e)),
+            Ok(c) => c
+        };
+        let contract_canonical = match api.canonical_address(contract_addr){
+            Err(e) => return Err(format!("contract address {} is invalid, {}",contract_addr,e)),
+            Ok(c) => c
+        };
+        return Ok(cosmwasm_std::Env{
             block: cosmwasm_std::BlockInfo{
-                height: 0,
-                time: 0,
-                chain_id: "okchain".to_string()
+                height: settings.block_height,
+                time: settings.block_time,
+                chain_id: settings.chain_id.clone()
             },
             message: cosmwasm_std::MessageInfo{
-                sender: cosmwasm_std::CanonicalAddr{
-                    0: cosmwasm_std::Binary::from_base64("b2tjaGFpbl9rYW1pZA==").unwrap()
-                },
-                sent_funds: vec![cosmwasm_std::Coin{
-                    denom: "okt".to_string(),
-                    amount: Uint128(100000000)
-                }]
+                sender: sender_canonical,
+                sent_funds: Vec::new()
             },
-            contract: Default::default()
-        }
+            contract: cosmwasm_std::ContractInfo{
+                address: contract_canonical
+            }
+        });
     }
 
     pub fn show_module_info(&self){
+        if watcher::is_json_output() {
+            let exports : Vec<String> = self.module.exports().iter().map(|e| e.name.to_string()).collect();
+            let imports : Vec<String> = self.module.imports().iter().map(|d| format!("{}->{}",d.namespace,d.name)).collect();
+            return watcher::logger_report("module",serde_json::json!({
+                "file": self.wasm_file, "backend": self.module.info().backend.to_string(), "exports": exports, "imports": imports
+            }));
+        }
         println!("showing wasm module info for [{}]",self.wasm_file);
         println!("backend : [{}]",self.module.info().backend);
 
@@ -104,90 +307,168 @@ impl ContractInstance
         }
     }
 
-    fn dump_result(key : &str,value : &[u8]){
-        let mut value_str = match std::str::from_utf8(value){
-            Ok(result) => result.to_string(),
-            _ => "".to_string()
+    //execute one call with full gas limit, return data or the error without printing
+    pub fn execute(&mut self,func_type:&str,param:&str) -> Result<CallResult,CallError>{
+        let expanded = match self.accounts.expand(param){
+            Err(e) => return Err(CallError::Dispatch(e)),
+            Ok(m) => m
         };
+        let param = expanded.as_str();
+        //like a transaction, a failed call or message leaves storage, bank and staking untouched
+        let before = self.save_state();
+        let result = self.execute_transaction(func_type,param);
+        if result.is_err() {
+            self.load_state(before);
+        }else if func_type == "init" || func_type == "handle" {
+            //oldest states are dropped so long runs like fuzzing do not grow without bound
+            if self.undo_stack.len() >= UNDO_LIMIT {
+                self.undo_stack.remove(0);
+            }
+            let recorded = self.recorder.as_ref().map(|r| r.step_count()).unwrap_or(0);
+            self.undo_stack.push((before,recorded));
+        }
+        return result;
+    }
 
-        if value_str.is_empty() {
-            for a in value.iter() {
-                write!(value_str, "{:02x}", a).expect("Not written");
+    fn execute_transaction(&mut self,func_type:&str,param:&str) -> Result<CallResult,CallError>{
+        //attached funds move from sender to contract before init and handle
+        if func_type == "init" || func_type == "handle" {
+            if let Err(e) = self.bank.transfer(&self.sender,&self.contract_addr,&self.env.message.sent_funds) {
+                return Err(CallError::Dispatch(e));
+            }
+        }
+        let result = self.execute_contract(func_type,param)?;
+        for msg in &result.messages {
+            if let Err(e) = self.dispatch_message(msg) {
+                return Err(CallError::Dispatch(format!("message {} failed, {}",serde_json::to_string(msg).unwrap_or_default(),e)));
             }
         }
+        return Ok(result);
+    }
 
-        println!("{} = {}",key,value_str);
+    //messages returned by contract are sent by contract itself
+    fn dispatch_message(&mut self,msg : &CosmosMsg<custom::CustomMsg>) -> Result<(),String>{
+        return match msg {
+            CosmosMsg::Bank(BankMsg::Send{from_address,to_address,amount}) => {
+                if *from_address != self.contract_addr {
+                    return Err(format!("contract can not send coins of {}",from_address));
+                }
+                self.bank.transfer(from_address,to_address,amount)
+            },
+            CosmosMsg::Staking(staking_msg) => self.staking.execute(&self.bank,&self.contract_addr,staking_msg),
+            CosmosMsg::Custom(custom_msg) => self.custom.execute(&self.contract_addr,custom_msg),
+            //messages of modules not simulated, like WasmMsg, are skipped so contracts calling others still run
+            _ => {
+                watcher::logger_unhandled_message(&self.accounts,&serde_json::to_value(msg).unwrap_or_default());
+                Ok(())
+            }
+        };
     }
-    pub fn call(&mut self,func_type:String, param:String) -> String{
-        println!("***************************call started***************************");
-        println!("executing func [{}] , params is {}",func_type,param);
+
+    //move chain forward, staking rewards accrue every block
+    pub fn advance_blocks(&mut self,blocks : u64){
+        self.env.block.height += blocks;
+        self.env.block.time += blocks * SECONDS_PER_BLOCK;
+        self.staking.accrue(blocks);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_block(blocks);
+        }
+    }
+
+    //metering of wasm code stops with a runtime error and no gas left, imported functions raise GasDepletion
+    fn vm_error(&self,err : VmError) -> CallError{
+        return match &err {
+            VmError::GasDepletion => CallError::OutOfGas(err.to_string()),
+            VmError::RuntimeErr{..} if self.instance.get_gas_left() == 0 => CallError::OutOfGas(err.to_string()),
+            _ => CallError::Vm(err.to_string())
+        };
+    }
+
+    fn execute_contract(&mut self,func_type:&str,param:&str) -> Result<CallResult,CallError>{
+        //every call starts with the full gas limit, gas is not used up across calls
+        self.instance.set_gas(self.gas_limit);
         let gas_init = self.instance.get_gas();
-        if func_type == "init" {
-            let init_result = cosmwasm_vm::call_init::<_,_,_,cosmwasm_std::Never>(&mut self.instance,&self.env,param.as_bytes());
+        let mut messages = Vec::new();
+        let mut log = Vec::new();
+        let data : Binary = if func_type == "init" {
+            let init_result = cosmwasm_vm::call_init::<_,_,_,custom::CustomMsg>(&mut self.instance,&self.env,param.as_bytes());
             let msg = match init_result {
                 Ok(data) => match data {
                     Ok(resp) => resp,
-                    Err(err) => {
-                        println!("Error {}",err);
-                        return "ERROR      :execute init failed".to_string();
-                    }
+                    Err(err) => return Err(CallError::Contract(err.to_string()))
                 },
-                Err(err) => {
-                    println!("Error {}",err);
-                    return "ERROR      :execute init failed".to_string();
-                }
+                Err(err) => return Err(self.vm_error(err))
             };
-            let data : Binary = match msg.data {
+            messages = msg.messages;
+            log = msg.log;
+            match msg.data {
                 None => Binary::from("".as_bytes()),
                 Some(d) => d
-            };
-            ContractInstance::dump_result("init msg.data:", data.0.as_slice());
+            }
         }else if func_type == "handle" {
-            let handle_result = cosmwasm_vm::call_handle::<_,_,_,cosmwasm_std::Never>(&mut self.instance,&self.env,param.as_bytes());
+            let handle_result = cosmwasm_vm::call_handle::<_,_,_,custom::CustomMsg>(&mut self.instance,&self.env,param.as_bytes());
             let msg = match handle_result {
                 Ok(data) => match data {
                     Ok(resp) => resp,
-                    Err(err) => {
-                        println!("Error {}",err);
-                        return "ERROR      :execute query failed".to_string();
-                    }
+                    Err(err) => return Err(CallError::Contract(err.to_string()))
                 },
-                Err(err) => {
-                    println!("Error {}",err);
-                    return "ERROR      :execute query failed".to_string();
-                }
+                Err(err) => return Err(self.vm_error(err))
             };
-
-            let data : Binary = match msg.data {
+            messages = msg.messages;
+            log = msg.log;
+            match msg.data {
                 None => Binary::from("".as_bytes()),
                 Some(d) => d
-            };
-            ContractInstance::dump_result("handle msg.data:", data.0.as_slice());
-
+            }
         }else if func_type == "query" {
             let query_result = cosmwasm_vm::call_query::<_,_,_>(&mut self.instance,param.as_bytes());
-            let msg = match query_result {
+            match query_result {
                 Ok(data) => match data {
                     Ok(resp) => resp,
-                    Err(err) => {
-                        println!("Error {}",err);
-                        return "ERROR      :execute query failed".to_string();
-                    }
+                    Err(err) => return Err(CallError::Contract(err.to_string()))
                 },
-                Err(err) => {
-                    println!("Error {}",err);
-                    return "ERROR      :execute query failed".to_string();
-                }
-            };
-
-            ContractInstance::dump_result("query msg.data:", msg.0.as_slice());
+                Err(err) => return Err(self.vm_error(err))
+            }
         }else {
-            println!("wrong dispatcher call {}",func_type);
-        }
+            return Err(CallError::Dispatch(format!("wrong dispatcher call {}",func_type)));
+        };
         let gas_used = gas_init - self.instance.get_gas();
-        println!("Gas used   : {}",gas_used);
-        println!("***************************call finished***************************");
+        return Ok(CallResult{
+            data: data.0,
+            gas_used,
+            messages,
+            log
+        });
+    }
+
+    pub fn call(&mut self,func_type:String, param:String) -> String{
+        if !self.run_call(func_type.as_str(),param.as_str()) {
+            return format!("ERROR      :execute {} failed",func_type);
+        }
         return "Execute Success".to_string();
     }
-}
 
+    //execute and print one call, return false if it failed
+    pub fn run_call(&mut self,func_type:&str, param:&str) -> bool{
+        watcher::logger_call_start(&self.accounts,func_type,self.sender.as_str(),param);
+        let executed = self.execute(func_type,param);
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_call(&self.env,&self.sender,self.gas_limit,func_type,param,&executed);
+        }
+        let result = match executed {
+            Ok(r) => r,
+            Err(err) => {
+                watcher::logger_call_error(func_type,err.to_string().as_str());
+                return false;
+            }
+        };
+        let log = result.log.iter().map(|l| (l.key.clone(),l.value.clone())).collect();
+        let messages = result.messages.iter().map(|m| serde_json::to_value(m).unwrap_or_default()).collect();
+        watcher::logger_call_result(&self.accounts,func_type,result.data.as_slice(),&log,&messages);
+        if func_type == "query" {
+            watcher::logger_query_response(&self.accounts,&self.analyzer.check_query_response(param,result.data.as_slice()));
+        }
+        watcher::logger_gas_used(result.gas_used);
+        return true;
+    }
+}
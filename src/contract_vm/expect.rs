@@ -0,0 +1,211 @@
+//expectations of scenario steps, every failed expectation is reported as one line
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::contract_vm::accounts;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Expect {
+    //call must succeed (default) or fail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success : Option<bool>,
+    //call must fail with an error containing this text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error : Option<String>,
+    //response data must equal this json
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data : Option<Value>,
+    //json path like `$.balances[0].amount` => value it must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path : Option<BTreeMap<String,Value>>,
+    //log attribute key => value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log : Option<BTreeMap<String,String>>,
+    //storage key => json value after the call, null means the key must not exist
+    //key is text like `config`, `ns:balances/alice` for length-prefixed namespaces or `hex:0006...`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage : Option<BTreeMap<String,Value>>,
+    //upper bound of gas used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas : Option<u64>,
+}
+
+//minimal json path, supports `$`, `.key`, `['key']` and `[index]`
+pub fn select<'a>(root : &'a Value,path : &str) -> Result<Option<&'a Value>,String>{
+    let mut rest = match path.strip_prefix('$') {
+        None => return Err(format!("json path {} must start with $",path)),
+        Some(r) => r
+    };
+    let mut current = root;
+    while !rest.is_empty() {
+        let (next,remain) = if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(|c : char| c == '.' || c == '[').unwrap_or(r.len());
+            if end == 0 {
+                return Err(format!("json path {} has empty key",path));
+            }
+            (current.get(&r[..end]),&r[end..])
+        }else if let Some(r) = rest.strip_prefix('[') {
+            let end = match r.find(']') {
+                None => return Err(format!("json path {} has unclosed [",path)),
+                Some(e) => e
+            };
+            let inner = &r[..end];
+            let quoted = inner.len() >= 2 && (inner.starts_with('\'') && inner.ends_with('\'') || inner.starts_with('"') && inner.ends_with('"'));
+            let next = if quoted {
+                current.get(&inner[1..inner.len() - 1])
+            }else {
+                match inner.parse::<usize>() {
+                    Err(_e) => return Err(format!("json path {} has wrong index [{}]",path,inner)),
+                    Ok(i) => current.get(i)
+                }
+            };
+            (next,&r[end + 1..])
+        }else {
+            return Err(format!("json path {} is wrong at {}",path,rest));
+        };
+        current = match next {
+            None => return Ok(None),
+            Some(v) => v
+        };
+        rest = remain;
+    }
+    return Ok(Some(current));
+}
+
+//numbers written in scenario match numbers serialized as strings like Uint128, at any depth
+fn same_value(expected : &Value,actual : &Value) -> bool{
+    return match (expected,actual) {
+        (Value::Number(n),Value::String(s)) => n.to_string() == *s,
+        (Value::Array(e),Value::Array(a)) => e.len() == a.len() && e.iter().zip(a.iter()).all(|(e,a)| same_value(e,a)),
+        (Value::Object(e),Value::Object(a)) => e.len() == a.len() && e.iter().all(|(k,v)| a.get(k).map(|x| same_value(v,x)).unwrap_or(false)),
+        _ => expected == actual
+    };
+}
+
+fn decode_hex(text : &str) -> Result<Vec<u8>,String>{
+    if text.len() % 2 != 0 {
+        return Err(format!("hex key {} has odd length",text));
+    }
+    return (0..text.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2],16).map_err(|_e| format!("hex key {} is not valid",text)))
+        .collect();
+}
+
+//raw storage key of expectation or contract fixture, namespaces are length-prefixed like cosmwasm-storage buckets and singletons
+pub fn storage_key(text : &str) -> Result<Vec<u8>,String>{
+    if let Some(hex) = text.strip_prefix("hex:") {
+        return decode_hex(hex);
+    }
+    let path = match text.strip_prefix("ns:") {
+        None => return Ok(text.as_bytes().to_vec()),
+        Some(p) => p
+    };
+    let parts : Vec<&str> = path.split('/').collect();
+    //a single namespace is a singleton, otherwise the last part is the key inside the namespaces
+    let (namespaces,key) = if parts.len() == 1 { (&parts[..],"") } else { (&parts[..parts.len() - 1],parts[parts.len() - 1]) };
+    let mut raw = Vec::new();
+    for ns in namespaces {
+        if ns.len() > 0xFFFF {
+            return Err(format!("namespace of key {} is too long",text));
+        }
+        raw.extend_from_slice(&(ns.len() as u16).to_be_bytes());
+        raw.extend_from_slice(ns.as_bytes());
+    }
+    raw.extend_from_slice(key.as_bytes());
+    return Ok(raw);
+}
+
+fn show(accounts : &accounts::Accounts,value : &Value) -> String{
+    return accounts::label(accounts,value.to_string().as_str());
+}
+
+impl Expect {
+    //check result of call, return a message for every failed expectation
+    pub fn check(&self,accounts : &accounts::Accounts,data : &Value,error : &Option<String>,gas_used : u64,log : &Vec<(String,String)>,storage : &BTreeMap<Vec<u8>,Vec<u8>>) -> Vec<String>{
+        let mut failures = Vec::new();
+        let want_success = self.success.unwrap_or(self.error.is_none());
+        match error {
+            Some(e) => {
+                if want_success {
+                    failures.push(format!("expected success, got error {}",accounts::label(accounts,e.as_str())));
+                }else if let Some(text) = &self.error {
+                    if !e.contains(text.as_str()) {
+                        failures.push(format!("expected error containing [{}], got {}",text,accounts::label(accounts,e.as_str())));
+                    }
+                }
+                //nothing else can be checked on a failed call
+                return failures;
+            },
+            None => {
+                if !want_success {
+                    failures.push(match &self.error {
+                        Some(text) => format!("expected error containing [{}], call succeeded",text),
+                        None => "expected failure, call succeeded".to_string()
+                    });
+                }
+            }
+        }
+        if let Some(expected) = &self.data {
+            if !same_value(expected,data) {
+                failures.push(format!("data: expected {}, got {}",show(accounts,expected),show(accounts,data)));
+            }
+        }
+        if let Some(paths) = &self.path {
+            for (path,expected) in paths {
+                match select(data,path.as_str()) {
+                    Err(e) => failures.push(e),
+                    Ok(None) => failures.push(format!("{}: not found in {}",path,show(accounts,data))),
+                    Ok(Some(actual)) => {
+                        if !same_value(expected,actual) {
+                            failures.push(format!("{}: expected {}, got {}",path,show(accounts,expected),show(accounts,actual)));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(logs) = &self.log {
+            for (key,expected) in logs {
+                match log.iter().find(|l| l.0 == *key) {
+                    None => failures.push(format!("log {}: not found",key)),
+                    Some(l) => {
+                        if l.1 != *expected {
+                            failures.push(format!("log {}: expected {}, got {}",key,expected,accounts::label(accounts,l.1.as_str())));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(items) = &self.storage {
+            for (key,expected) in items {
+                let raw = match storage_key(key.as_str()) {
+                    Err(e) => {
+                        failures.push(e);
+                        continue;
+                    },
+                    Ok(k) => k
+                };
+                let actual = storage.get(&raw).map(|v| match serde_json::from_slice(v.as_slice()) {
+                    Ok(json) => json,
+                    Err(_e) => Value::String(String::from_utf8_lossy(v.as_slice()).to_string())
+                });
+                match (expected,actual) {
+                    (Value::Null,None) => {},
+                    (Value::Null,Some(a)) => failures.push(format!("storage {}: expected no value, got {}",key,show(accounts,&a))),
+                    (_,None) => failures.push(format!("storage {}: expected {}, got no value",key,show(accounts,expected))),
+                    (_,Some(a)) => {
+                        if !same_value(expected,&a) {
+                            failures.push(format!("storage {}: expected {}, got {}",key,show(accounts,expected),show(accounts,&a)));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(limit) = self.gas {
+            if gas_used > limit {
+                failures.push(format!("gas: used {}, more than {}",gas_used,limit));
+            }
+        }
+        return failures;
+    }
+}
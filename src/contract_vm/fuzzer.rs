@@ -0,0 +1,397 @@
+//schema driven fuzzer, send random but schema-valid messages to contract and report vm failures
+
+use std::fmt;
+use serde_json::{Value, Map};
+use crate::contract_vm::engine::{ContractInstance, CallError, ChainState};
+use crate::contract_vm::{validator, watcher};
+
+static MAX_DEPTH: usize = 6;
+static MAX_SHRINK_ATTEMPTS: usize = 500;
+static LONG_STRING_LEN: usize = 4096;
+
+//xorshift64*, good enough for fuzzing and reproducible by seed
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed : u64) -> Self{
+        return Rng{ state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed } };
+    }
+
+    pub fn next_u64(&mut self) -> u64{
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        return x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+    }
+
+    pub fn below(&mut self,n : usize) -> usize{
+        if n == 0 {
+            return 0;
+        }
+        return (self.next_u64() % n as u64) as usize;
+    }
+
+    pub fn pick<'a,T>(&mut self,items : &'a [T]) -> &'a T{
+        let idx = self.below(items.len());
+        return &items[idx];
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FindingKind {
+    Trap,
+    Panic,
+    OutOfGas,
+    NonDeterministic,
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FindingKind::Trap => "wasm trap",
+            FindingKind::Panic => "panic",
+            FindingKind::OutOfGas => "out of gas",
+            FindingKind::NonDeterministic => "non-deterministic result",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub struct Finding {
+    pub kind : FindingKind,
+    pub call_type : String,
+    pub message : Value,
+    pub minimal : Value,
+    pub error : String,
+}
+
+fn short_name(reference : &str) -> &str{
+    return match reference.rfind('/') {
+        None => reference,
+        Some(idx) => &reference[idx + 1..],
+    };
+}
+
+fn random_string(rng : &mut Rng) -> String{
+    return match rng.below(5) {
+        0 => "".to_string(),
+        1 => "a".repeat(LONG_STRING_LEN),
+        2 => "ü∑ 😀\"\\".to_string(),
+        _ => {
+            let chars = b"abcdefghijklmnopqrstuvwxyz0123456789";
+            let len = 1 + rng.below(16);
+            (0..len).map(|_| *rng.pick(chars) as char).collect()
+        }
+    };
+}
+
+fn random_integer(rng : &mut Rng,format : Option<&str>) -> Value{
+    let max : u64 = match format {
+        Some("uint8") => u8::MAX as u64,
+        Some("uint16") => u16::MAX as u64,
+        Some("uint32") => u32::MAX as u64,
+        Some("int32") => i32::MAX as u64,
+        Some("int64") => i64::MAX as u64,
+        _ => u64::MAX,
+    };
+    return match rng.below(4) {
+        0 => Value::from(0),
+        1 => Value::from(max),
+        2 => Value::from(1),
+        _ => {
+            if max == u64::MAX {
+                Value::from(rng.next_u64())
+            }else {
+                Value::from(rng.next_u64() % (max + 1))
+            }
+        },
+    };
+}
+
+fn random_definition(rng : &mut Rng,name : &str) -> Option<Value>{
+    let value = match name {
+        "Uint128" => match rng.below(4) {
+            0 => "0".to_string(),
+            1 => u128::MAX.to_string(),
+            2 => "1".to_string(),
+            _ => rng.next_u64().to_string(),
+        },
+        "Decimal" => match rng.below(3) {
+            0 => "0".to_string(),
+            1 => "340282366920938463463.374607431768211455".to_string(),
+            _ => format!("{}.{}",rng.below(1000),rng.below(1000)),
+        },
+        "HumanAddr" => random_string(rng),
+        "Binary" => match rng.below(2) {
+            0 => "".to_string(),
+            _ => "AAECAwQFBgcICQ==".to_string(),
+        },
+        _ => return None
+    };
+    return Some(Value::String(value));
+}
+
+fn random_of_type(rng : &mut Rng,root : &Value,schema : &Value,type_name : &str,depth : usize) -> Value{
+    return match type_name {
+        "null" => Value::Null,
+        "boolean" => Value::Bool(rng.below(2) == 1),
+        "string" => Value::String(random_string(rng)),
+        "integer" => random_integer(rng,schema.get("format").and_then(|f| f.as_str())),
+        "number" => Value::from(rng.below(1_000_000) as f64 / 100.0),
+        "array" => {
+            let items = match schema.get("items"){
+                None => return Value::Array(Vec::new()),
+                Some(i) => i
+            };
+            let len = rng.below(4);
+            Value::Array((0..len).map(|_| random_value(rng,root,items,depth + 1)).collect())
+        },
+        "object" => {
+            let required : Vec<&str> = schema.get("required").and_then(|r| r.as_array())
+                .map(|r| r.iter().filter_map(|n| n.as_str()).collect()).unwrap_or_default();
+            let mut obj = Map::new();
+            if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+                for (k,v) in props {
+                    //optional fields are left out half of the time
+                    if !required.contains(&k.as_str()) && rng.below(2) == 0 {
+                        continue;
+                    }
+                    obj.insert(k.clone(),random_value(rng,root,v,depth + 1));
+                }
+            }
+            Value::Object(obj)
+        },
+        _ => Value::Null
+    };
+}
+
+//random value which is valid against schema node
+pub fn random_value(rng : &mut Rng,root : &Value,schema : &Value,depth : usize) -> Value{
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+    if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+        if let Some(v) = random_definition(rng,short_name(reference)) {
+            return v;
+        }
+        return match validator::resolve_ref(root,reference){
+            None => Value::Null,
+            Some(target) => random_value(rng,root,target,depth + 1)
+        };
+    }
+    if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+        if let Some(first) = all.first() {
+            return random_value(rng,root,first,depth + 1);
+        }
+    }
+    if let Some(any) = schema.get("anyOf").or(schema.get("oneOf")).and_then(|a| a.as_array()) {
+        if any.is_empty() {
+            return Value::Null;
+        }
+        let alt = rng.pick(any.as_slice());
+        return random_value(rng,root,alt,depth + 1);
+    }
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.is_empty() {
+            return rng.pick(options.as_slice()).clone();
+        }
+    }
+    return match schema.get("type"){
+        Some(Value::String(t)) => random_of_type(rng,root,schema,t.as_str(),depth),
+        Some(Value::Array(types)) => {
+            let names : Vec<&str> = types.iter().filter_map(|t| t.as_str()).collect();
+            if names.is_empty() {
+                return Value::Null;
+            }
+            let t = *rng.pick(names.as_slice());
+            random_of_type(rng,root,schema,t,depth)
+        },
+        _ => Value::Null
+    };
+}
+
+fn classify(err : &CallError) -> Option<FindingKind>{
+    let msg = match err {
+        CallError::OutOfGas(_) => return Some(FindingKind::OutOfGas),
+        CallError::Vm(m) => m.to_lowercase(),
+        _ => return None
+    };
+    if msg.contains("unreachable") || msg.contains("panic") {
+        return Some(FindingKind::Panic);
+    }
+    return Some(FindingKind::Trap);
+}
+
+//outcome of one call and chain state after it, used to compare two runs of same message
+fn run_once(engine : &mut ContractInstance,call_type : &str,msg : &str) -> (Result<(Vec<u8>,u64),CallError>,ChainState){
+    let result = engine.execute(call_type,msg).map(|r| (r.data,r.gas_used));
+    return (result,engine.save_state());
+}
+
+//run message twice from the same state, leave state as after the run
+fn probe(engine : &mut ContractInstance,call_type : &str,msg : &Value,state : &ChainState) -> Option<(FindingKind,String)>{
+    let text = msg.to_string();
+    engine.load_state(state.clone());
+    let (first,first_state) = run_once(engine,call_type,text.as_str());
+    if let Err(e) = &first {
+        if let Some(kind) = classify(e) {
+            return Some((kind,e.to_string()));
+        }
+    }
+    engine.load_state(state.clone());
+    let (second,second_state) = run_once(engine,call_type,text.as_str());
+    if first != second || first_state != second_state {
+        return Some((FindingKind::NonDeterministic,format!("first run {:?}, second run {:?}",
+            first.map(|r| r.1),second.map(|r| r.1))));
+    }
+    return None;
+}
+
+//simpler versions of value, strings and numbers shrink toward empty and zero, arrays lose items
+fn simplify(value : &Value) -> Vec<Value>{
+    let mut out = Vec::new();
+    match value {
+        Value::String(s) => {
+            if !s.is_empty() {
+                out.push(Value::String("".to_string()));
+                let half : String = s.chars().take(s.chars().count() / 2).collect();
+                if !half.is_empty() {
+                    out.push(Value::String(half));
+                }
+            }
+        },
+        Value::Number(n) => {
+            if n.as_u64() != Some(0) {
+                out.push(Value::from(0));
+            }
+        },
+        Value::Bool(b) => {
+            if *b {
+                out.push(Value::Bool(false));
+            }
+        },
+        Value::Array(arr) => {
+            for i in 0..arr.len() {
+                let mut smaller = arr.clone();
+                smaller.remove(i);
+                out.push(Value::Array(smaller));
+            }
+            for i in 0..arr.len() {
+                for cand in simplify(&arr[i]) {
+                    let mut changed = arr.clone();
+                    changed[i] = cand;
+                    out.push(Value::Array(changed));
+                }
+            }
+        },
+        Value::Object(obj) => {
+            for (k,v) in obj {
+                for cand in simplify(v) {
+                    let mut changed = obj.clone();
+                    changed.insert(k.clone(),cand);
+                    out.push(Value::Object(changed));
+                }
+            }
+        },
+        Value::Null => {}
+    }
+    return out;
+}
+
+fn shrink(engine : &mut ContractInstance,call_type : &str,msg : &Value,kind : FindingKind,state : &ChainState) -> Value{
+    let mut current = msg.clone();
+    let mut attempts = 0;
+    loop {
+        let mut progressed = false;
+        for cand in simplify(&current) {
+            attempts += 1;
+            if attempts > MAX_SHRINK_ATTEMPTS {
+                return current;
+            }
+            if let Some((k,_)) = probe(engine,call_type,&cand,state) {
+                if k == kind {
+                    current = cand;
+                    progressed = true;
+                    break;
+                }
+            }
+        }
+        if !progressed {
+            return current;
+        }
+    }
+}
+
+fn random_message(rng : &mut Rng,engine : &ContractInstance,call_type : &str) -> Option<Value>{
+    let schema = match engine.analyzer.message_title_of(call_type).and_then(|t| engine.analyzer.map_of_schema.get(&t)){
+        None => return None,
+        Some(s) => s
+    };
+    return Some(random_value(rng,schema,schema,0));
+}
+
+//send `iterations` random handle and query messages, state keeps changes of successful handle calls
+pub fn run_fuzz(engine : &mut ContractInstance,iterations : usize,seed : u64) -> Vec<Finding>{
+    let mut rng = Rng::new(seed);
+    let mut findings : Vec<Finding> = Vec::new();
+    let call_types : Vec<&str> = ["handle","query"].iter().cloned()
+        .filter(|c| engine.analyzer.message_title_of(c).is_some()).collect();
+    if call_types.is_empty() {
+        watcher::logger_info("no HandleMsg or QueryMsg schema found, nothing to fuzz");
+        return findings;
+    }
+    watcher::set_quiet(true);
+    //calls of the fuzzer are kept out of the undo history of the session
+    let undo_stack = std::mem::take(&mut engine.undo_stack);
+    for i in 0..iterations {
+        let call_type = *rng.pick(call_types.as_slice());
+        let msg = match random_message(&mut rng,engine,call_type){
+            None => continue,
+            Some(m) => m
+        };
+        let state = engine.save_state();
+        match probe(engine,call_type,&msg,&state) {
+            None => {},
+            Some((kind,error)) => {
+                watcher::logger_info(&format!("[{}/{}] {} found by {} {}",i + 1,iterations,kind,call_type,msg));
+                let minimal = shrink(engine,call_type,&msg,kind,&state);
+                findings.push(Finding{
+                    kind,
+                    call_type: call_type.to_string(),
+                    message: msg,
+                    minimal,
+                    error
+                });
+                //failed call must not change state
+                engine.load_state(state);
+            }
+        }
+    }
+    engine.undo_stack = undo_stack;
+    watcher::set_quiet(false);
+    return findings;
+}
+
+pub fn print_findings(findings : &Vec<Finding>){
+    if watcher::is_json_output() {
+        let items : Vec<Value> = findings.iter().map(|f| serde_json::json!({
+            "kind": f.kind.to_string(), "call_type": f.call_type, "error": f.error, "message": f.message, "minimal": f.minimal
+        })).collect();
+        return watcher::logger_report("fuzz_report",serde_json::json!({"findings": items}));
+    }
+    println!("=============================== fuzz report ===============================");
+    if findings.is_empty() {
+        println!("no finding");
+        return;
+    }
+    for (i,f) in findings.iter().enumerate() {
+        println!("#{} {} on {}",i + 1,f.kind,f.call_type);
+        println!("\terror   : {}",f.error);
+        println!("\tmessage : {}",f.message);
+        println!("\tminimal : {}",f.minimal);
+    }
+}
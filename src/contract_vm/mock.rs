@@ -1,4 +1,6 @@
 use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::rc::Rc;
 #[cfg(feature = "iterator")]
 use std::{
     iter,
@@ -6,26 +8,37 @@ use std::{
 };
 
 
-use cosmwasm_vm::{ReadonlyStorage, FfiResult, Storage, Api, FfiError, Extern};
-use cosmwasm_std::{HumanAddr, CanonicalAddr, Binary, Coin};
-use crate::contract_vm::watcher;
+use cosmwasm_vm::{ReadonlyStorage, FfiResult, Storage, Api, FfiError, Extern, Querier};
+use cosmwasm_std::{from_slice, HumanAddr, CanonicalAddr, Binary, QuerierResult, QueryRequest, SystemError};
+use bech32::{FromBase32, ToBase32};
+use crate::contract_vm::{accounts, bank, staking, custom, contracts, watcher};
 
-///mock storage
-#[derive(Default, Debug)]
+///mock storage, cloned storage shares the same data so simulator can inspect it beside the vm
+#[derive(Default, Debug, Clone)]
 pub struct MockStorage {
-    data: BTreeMap<Vec<u8>, Vec<u8>>,
+    data: Rc<RefCell<BTreeMap<Vec<u8>, Vec<u8>>>>,
+    //labels addresses in logged changes
+    accounts: accounts::Accounts,
 }
 
 impl MockStorage {
-    pub fn new() -> Self {
-        MockStorage::default()
+    pub fn new(accounts: accounts::Accounts) -> Self {
+        MockStorage { data: Rc::default(), accounts }
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.data.borrow().clone()
+    }
+
+    pub fn restore(&self, data: BTreeMap<Vec<u8>, Vec<u8>>) {
+        *self.data.borrow_mut() = data;
     }
 }
 
 
 impl ReadonlyStorage for MockStorage {
     fn get(&self, key: &[u8]) -> FfiResult<Option<Vec<u8>>> {
-        Ok(self.data.get(key).cloned())
+        Ok(self.data.borrow().get(key).cloned())
     }
 
     #[cfg(feature = "iterator")]
@@ -46,10 +59,10 @@ impl ReadonlyStorage for MockStorage {
             _ => {}
         }
 
-        let iter = self.data.range(bounds);
+        let items: Vec<KV> = self.data.borrow().range(bounds).map(clone_item).collect();
         Ok(match order {
-            Order::Ascending => Box::new(iter.map(clone_item).map(FfiResult::Ok)),
-            Order::Descending => Box::new(iter.rev().map(clone_item).map(FfiResult::Ok)),
+            Order::Ascending => Box::new(items.into_iter().map(FfiResult::Ok)),
+            Order::Descending => Box::new(items.into_iter().rev().map(FfiResult::Ok)),
         })
     }
 }
@@ -57,13 +70,14 @@ impl ReadonlyStorage for MockStorage {
 impl Storage for MockStorage {
 
     fn set(&mut self, key: &[u8], value: &[u8]) -> FfiResult<()> {
-        self.data.insert(key.to_vec(), value.to_vec());
-        watcher::logger_storage_event_insert(key,value);
+        self.data.borrow_mut().insert(key.to_vec(), value.to_vec());
+        watcher::logger_storage_event_insert(&self.accounts,key,value);
         Ok(())
     }
 
     fn remove(&mut self, key: &[u8]) -> FfiResult<()> {
-        self.data.remove(key);
+        self.data.borrow_mut().remove(key);
+        watcher::logger_storage_event_remove(&self.accounts,key);
 
         Ok(())
     }
@@ -73,15 +87,83 @@ impl MockStorage{
 
 }
 
+//longest human readable part of a bech32 address
+const MAX_PREFIX_LENGTH: usize = 83;
+
+//bech32 prefix kept inline, Api must be Copy and Send so it can not hold a String or Rc
+#[derive(Copy, Clone)]
+struct Prefix {
+    bytes: [u8; MAX_PREFIX_LENGTH],
+    len: usize,
+}
+
+impl Prefix {
+    fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
 //mock api
+//canonical address is the raw human address padded with zeros, or the 20 bytes decoded from bech32 if prefix is set
 #[derive(Copy, Clone)]
 pub struct MockApi {
     canonical_length: usize,
+    prefix: Option<Prefix>,
 }
 
 impl MockApi {
     pub fn new(canonical_length: usize) -> Self {
-        MockApi { canonical_length }
+        MockApi { canonical_length, prefix: None }
+    }
+
+    pub fn new_bech32(canonical_length: usize, prefix: &str) -> Result<Self, String> {
+        if prefix.is_empty() || prefix.len() > MAX_PREFIX_LENGTH {
+            return Err(format!("address prefix {} must be 1 to {} characters", prefix, MAX_PREFIX_LENGTH));
+        }
+        let mut bytes = [0u8; MAX_PREFIX_LENGTH];
+        bytes[..prefix.len()].copy_from_slice(prefix.as_bytes());
+        Ok(MockApi { canonical_length, prefix: Some(Prefix { bytes, len: prefix.len() }) })
+    }
+
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_ref().map(|p| p.as_str())
+    }
+
+    //deterministic valid address for seed, like a test account named by seed
+    pub fn test_address(&self, seed: &str) -> HumanAddr {
+        match self.prefix {
+            Some(_) => {
+                let mut bytes = vec![0u8; self.canonical_length];
+                let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+                for (i, b) in bytes.iter_mut().enumerate() {
+                    for c in seed.bytes().chain(std::iter::once(i as u8)) {
+                        hash ^= c as u64;
+                        hash = hash.wrapping_mul(0x0100_0000_01b3);
+                    }
+                    *b = (hash >> 24) as u8;
+                }
+                self.human_address(&CanonicalAddr(Binary(bytes))).unwrap()
+            }
+            None => {
+                let mut human = format!("addr{}", seed);
+                human.truncate(self.canonical_length);
+                HumanAddr(human)
+            }
+        }
+    }
+
+    fn bech32_canonical(&self, prefix: &str, human: &HumanAddr) -> FfiResult<CanonicalAddr> {
+        let (hrp, data) = bech32::decode(human.as_str())
+            .map_err(|e| FfiError::other(format!("Invalid input: bech32 decode failed, {}", e)))?;
+        if hrp != prefix {
+            return Err(FfiError::other(format!("Invalid input: address prefix {} not match {}", hrp, prefix)));
+        }
+        let bytes = Vec::<u8>::from_base32(&data)
+            .map_err(|e| FfiError::other(format!("Invalid input: bech32 data invalid, {}", e)))?;
+        if bytes.len() != self.canonical_length {
+            return Err(FfiError::other("Invalid input: canonical address length not correct"));
+        }
+        Ok(CanonicalAddr(Binary(bytes)))
     }
 }
 
@@ -93,13 +175,22 @@ impl Default for MockApi {
 
 impl Api for MockApi {
     fn canonical_address(&self, human: &HumanAddr) -> FfiResult<CanonicalAddr> {
-        // Dummy input validation. This is more sophisticated for formats like bech32, where format and checksum are validated.
+        if let Some(prefix) = self.prefix() {
+            return self.bech32_canonical(prefix, human);
+        }
+        // canonical address is the utf-8 bytes padded with zeros, so zero bytes and surrounding spaces can not round-trip
         if human.len() < 3 {
             return Err(FfiError::other("Invalid input: human address too short"));
         }
         if human.len() > self.canonical_length {
             return Err(FfiError::other("Invalid input: human address too long"));
         }
+        if human.as_str().contains('\0') {
+            return Err(FfiError::other("Invalid input: human address contains zero byte"));
+        }
+        if human.as_str().trim() != human.as_str() {
+            return Err(FfiError::other("Invalid input: human address has leading or trailing whitespace"));
+        }
 
         let mut out = Vec::from(human.as_str());
         let append = self.canonical_length - out.len();
@@ -116,28 +207,76 @@ impl Api for MockApi {
             ));
         }
 
-        // remove trailing 0's (TODO: fix this - but fine for first tests)
-        let trimmed: Vec<u8> = canonical
+        if let Some(prefix) = self.prefix() {
+            let human = bech32::encode(prefix, canonical.as_slice().to_base32())
+                .map_err(|e| FfiError::other(format!("bech32 encode failed, {}", e)))?;
+            return Ok(HumanAddr(human));
+        }
+
+        // remove trailing 0's of padding only, a zero byte inside was never produced by canonical_address
+        let end = canonical
             .as_slice()
             .iter()
-            .cloned()
-            .filter(|&x| x != 0)
-            .collect();
+            .rposition(|&x| x != 0)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let trimmed = &canonical.as_slice()[..end];
+        if trimmed.len() < 3 {
+            return Err(FfiError::other("Invalid input: canonical address too short"));
+        }
+        if trimmed.contains(&0u8) {
+            return Err(FfiError::other("Invalid input: canonical address contains zero byte before padding"));
+        }
         // decode UTF-8 bytes into string
-        let human = String::from_utf8(trimmed)
+        let human = String::from_utf8(trimmed.to_vec())
             .map_err(|_| FfiError::other("Could not parse human address result as utf-8"))?;
         Ok(HumanAddr(human))
     }
 }
 
-pub fn new_mock(canonical_length: usize,
-                contract_balance: &[Coin],
-                contract_addr : &str
-) -> Extern<MockStorage,MockApi,cosmwasm_vm::testing::MockQuerier>{
-    let human_addr = HumanAddr::from(contract_addr);
+//mock querier, answers bank, staking, custom and wasm queries from the modules of simulation
+pub struct MockQuerier {
+    bank: bank::Bank,
+    staking: staking::Staking,
+    custom: custom::CustomHandler,
+    contracts: contracts::Contracts,
+}
+
+impl MockQuerier {
+    pub fn new(bank: bank::Bank, staking: staking::Staking, custom: custom::CustomHandler, contracts: contracts::Contracts) -> Self {
+        MockQuerier { bank, staking, custom, contracts }
+    }
+}
+
+impl Querier for MockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<custom::CustomMsg> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(SystemError::InvalidRequest {
+                    error: format!("Parsing QueryRequest: {}", e),
+                })
+            }
+        };
+        match &request {
+            QueryRequest::Bank(bank_query) => self.bank.query(bank_query),
+            QueryRequest::Staking(staking_query) => self.staking.query(staking_query),
+            QueryRequest::Custom(custom_query) => self.custom.query(custom_query),
+            QueryRequest::Wasm(wasm_query) => self.contracts.query(wasm_query),
+        }
+    }
+}
+
+pub fn new_mock(api: MockApi,
+                bank: bank::Bank,
+                staking: staking::Staking,
+                custom: custom::CustomHandler,
+                contracts: contracts::Contracts,
+                accounts: accounts::Accounts
+) -> Extern<MockStorage,MockApi,MockQuerier>{
     Extern {
-        storage: MockStorage::default(),
-        api: MockApi::new(canonical_length),
-        querier: cosmwasm_vm::testing::MockQuerier::new(&[(&human_addr, contract_balance)]),
+        storage: MockStorage::new(accounts),
+        api,
+        querier: MockQuerier::new(bank, staking, custom, contracts),
     }
-}
\ No newline at end of file
+}
@@ -5,8 +5,24 @@ pub mod engine;
 pub mod analyzer;
 pub mod mock;
 pub mod watcher;
+pub mod validator;
+pub mod template;
+pub mod fuzzer;
+pub mod probe;
+pub mod settings;
+pub mod accounts;
+pub mod bank;
+pub mod staking;
+pub mod custom;
+pub mod contracts;
+pub mod scenario;
+pub mod expect;
+pub mod server;
+pub mod console;
+pub mod session;
+pub mod config;
 
-pub fn build_simulation(wasmfile: &str)-> Result<ContractInstance,String>{
-    let wasmer = engine::ContractInstance::new_instance(wasmfile);
+pub fn build_simulation(wasmfile: &str,settings: &settings::Settings)-> Result<ContractInstance,String>{
+    let wasmer = engine::ContractInstance::new_instance(wasmfile,settings);
     return wasmer;
 }
\ No newline at end of file
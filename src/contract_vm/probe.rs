@@ -0,0 +1,122 @@
+//schema conformance probe, send one message per schema variant and report variants the contract can not parse
+
+use crate::contract_vm::engine::{ContractInstance, CallError};
+use crate::contract_vm::{template, watcher};
+
+pub struct ProbeIssue {
+    //true if message could not be parsed, false for other vm errors like out of gas or panic
+    pub drift : bool,
+    pub call_type : String,
+    pub variant : String,
+    pub message : String,
+    pub error : String,
+}
+
+fn is_parse_error(err : &CallError) -> bool{
+    return match err {
+        CallError::Contract(e) => e.contains("ParseErr") || e.contains("Error parsing"),
+        //only vm errors of deserializing the message, out of gas or panics of contract logic are not drift
+        CallError::Vm(e) => {
+            let e = e.to_lowercase();
+            e.contains("error parsing") || e.contains("parseerr") || e.contains("deserializ")
+        },
+        CallError::OutOfGas(_) | CallError::Dispatch(_) => false,
+    };
+}
+
+//outcome of one variant, error of an accepted variant is the rejection by contract logic
+fn print_variant(call_type : &str,variant : &str,status : &str,error : Option<&CallError>){
+    if watcher::is_json_output() {
+        return watcher::logger_report("probe",serde_json::json!({
+            "call_type": call_type, "variant": variant, "status": status, "error": error.map(|e| e.to_string())
+        }));
+    }
+    match error {
+        Some(e) => println!("{:<8} {:<24} {:<7}{}",call_type,variant,status,e),
+        None => println!("{:<8} {:<24} {}",call_type,variant,status)
+    }
+}
+
+fn probe_call_type(engine : &mut ContractInstance,call_type : &str,issues : &mut Vec<ProbeIssue>) -> usize{
+    let variants = match engine.analyzer.message_title_of(call_type).and_then(|t| engine.analyzer.map_of_schema.get(&t)){
+        None => {
+            watcher::logger_info(&format!("{:<8} no schema, skipped",call_type));
+            return 0;
+        },
+        Some(schema) => template::message_variants(schema)
+    };
+    let state = engine.save_state();
+    for (variant,msg) in &variants {
+        engine.load_state(state.clone());
+        let text = msg.to_string();
+        match engine.execute(call_type,text.as_str()) {
+            Err(e) => {
+                let drift = is_parse_error(&e);
+                if drift || matches!(e,CallError::Vm(_) | CallError::OutOfGas(_)) {
+                    print_variant(call_type,variant,if drift { "DRIFT" } else { "VM ERR" },Some(&e));
+                    issues.push(ProbeIssue{
+                        drift,
+                        call_type: call_type.to_string(),
+                        variant: variant.clone(),
+                        message: text,
+                        error: e.to_string()
+                    });
+                }else {
+                    //parsed fine, rejected by contract logic
+                    print_variant(call_type,variant,"OK",Some(&e));
+                }
+            },
+            Ok(_) => print_variant(call_type,variant,"OK",None),
+        }
+    }
+    //init keeps its state so handle and query are probed against an initialized contract
+    if call_type != "init" {
+        engine.load_state(state);
+    }
+    return variants.len();
+}
+
+//probe init, handle and query variants in order, return variants that drifted or failed in vm
+pub fn run_probe(engine : &mut ContractInstance) -> Vec<ProbeIssue>{
+    let mut issues = Vec::new();
+    watcher::set_quiet(true);
+    //calls of the probe are kept out of the undo history of the session
+    let undo_stack = std::mem::take(&mut engine.undo_stack);
+    let mut count = 0;
+    for call_type in &["init","handle","query"] {
+        count += probe_call_type(engine,call_type,&mut issues);
+    }
+    engine.undo_stack = undo_stack;
+    watcher::set_quiet(false);
+    let drifted = issues.iter().filter(|i| i.drift).count();
+    watcher::logger_info(&format!("probed {} variants, {} drifted from schema, {} failed in vm",count,drifted,issues.len() - drifted));
+    return issues;
+}
+
+fn print_section(title : &str,issues : &[&ProbeIssue]){
+    if issues.is_empty() {
+        return;
+    }
+    println!("=============================== {} ===============================",title);
+    for i in issues {
+        println!("{} {} : {}",i.call_type,i.variant,i.error);
+        println!("\tmessage : {}",i.message);
+    }
+}
+
+pub fn print_issues(issues : &Vec<ProbeIssue>){
+    let (drifted,failed) : (Vec<&ProbeIssue>,Vec<&ProbeIssue>) = issues.iter().partition(|i| i.drift);
+    if watcher::is_json_output() {
+        let items = |list : &[&ProbeIssue]| -> Vec<serde_json::Value> {
+            list.iter().map(|i| serde_json::json!({
+                "call_type": i.call_type, "variant": i.variant, "message": i.message, "error": i.error
+            })).collect()
+        };
+        return watcher::logger_report("probe_report",serde_json::json!({"drifted": items(&drifted), "vm_errors": items(&failed)}));
+    }
+    if drifted.is_empty() {
+        println!("schema matches contract");
+    }
+    print_section("schema drift",&drifted);
+    print_section("vm errors",&failed);
+}
@@ -0,0 +1,379 @@
+//scenario runner, executes ordered steps from a yaml or json file without any input
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use cosmwasm_std::{Coin, Env, HumanAddr};
+use crate::contract_vm::engine::{ContractInstance, CallResult, CallError};
+use crate::contract_vm::{accounts, analyzer, watcher};
+use crate::contract_vm::expect::Expect;
+use crate::contract_vm::config::Config;
+use crate::contract_vm::settings::Settings;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct EnvStep {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height : Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time : Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id : Option<String>,
+}
+
+//one step changes env, block, balances and then sends at most one call
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Step {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name : Option<String>,
+    //sender, funds and gas limit of this step only, defaults of simulation are used if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funds : Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_limit : Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env : Option<EnvStep>,
+    //number of blocks to move forward
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block : Option<u64>,
+    //account alias or address => coins like 100okt,5uatom
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance : Option<BTreeMap<String,String>>,
+    //message of call, json object or json text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init : Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle : Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query : Option<Value>,
+    //expectations of call, call must succeed if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect : Option<Expect>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Scenario {
+    //settings of the instance written like a config file, they replace the command line flags when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setup : Option<Config>,
+    pub steps : Vec<Step>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StepResult {
+    pub step : usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name : Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call : Option<String>,
+    pub success : bool,
+    //response data, json if it can be parsed
+    pub data : Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error : Option<String>,
+    pub gas_used : u64,
+    pub log : Vec<(String,String)>,
+    pub passed : bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures : Vec<String>,
+}
+
+pub fn load_scenario(file : &str) -> Result<Scenario,String>{
+    let data = analyzer::load_data_from_file(file)?;
+    let parsed = if file.ends_with(".json") {
+        serde_json::from_slice(data.as_slice()).map_err(|e| e.to_string())
+    }else {
+        serde_yaml::from_slice(data.as_slice()).map_err(|e| e.to_string())
+    };
+    let mut scenario : Scenario = match parsed {
+        Err(e) => return Err(format!("scenario {} is not valid, {}",file,e)),
+        Ok(s) => s
+    };
+    if let Some(setup) = &mut scenario.setup {
+        setup.resolve_paths(Path::new(file).parent().unwrap_or_else(|| Path::new("")));
+    }
+    return Ok(scenario);
+}
+
+pub fn save_scenario(scenario : &Scenario,file : &str) -> Result<(),String>{
+    let text = if file.ends_with(".json") {
+        serde_json::to_string_pretty(scenario).map_err(|e| e.to_string())?
+    }else {
+        serde_yaml::to_string(scenario).map_err(|e| e.to_string())?
+    };
+    return std::fs::write(file,text).map_err(|e| format!("can not write scenario {}, {}",file,e));
+}
+
+impl Step {
+    //call type and message text of step, error if more than one call is set
+    pub fn call(&self) -> Result<Option<(&'static str,String)>,String>{
+        let mut calls = Vec::new();
+        for (call_type,msg) in vec![("init",&self.init),("handle",&self.handle),("query",&self.query)] {
+            if let Some(m) = msg {
+                let text = match m {
+                    Value::String(s) => s.clone(),
+                    _ => m.to_string()
+                };
+                calls.push((call_type,text));
+            }
+        }
+        if calls.len() > 1 {
+            return Err("only one of init, handle and query can be set in a step".to_string());
+        }
+        return Ok(calls.pop());
+    }
+}
+
+fn data_value(data : &[u8]) -> Value{
+    return match serde_json::from_slice(data){
+        Ok(v) => v,
+        Err(_e) => Value::String(String::from_utf8_lossy(data).to_string())
+    };
+}
+
+fn apply_setup(engine : &mut ContractInstance,step : &Step) -> Result<(),String>{
+    if let Some(env) = &step.env {
+        if let Some(height) = env.height {
+            engine.env.block.height = height;
+        }
+        if let Some(time) = env.time {
+            engine.env.block.time = time;
+        }
+        if let Some(chain_id) = &env.chain_id {
+            engine.env.block.chain_id = chain_id.clone();
+        }
+    }
+    if let Some(blocks) = step.block {
+        engine.advance_blocks(blocks);
+    }
+    if let Some(balances) = &step.balance {
+        for (who,coins) in balances {
+            let human = engine.accounts.resolve(who.as_str());
+            engine.bank.set_balance(&human,accounts::parse_coins(coins.as_str())?);
+        }
+    }
+    return Ok(());
+}
+
+//run one step, sender, funds and gas limit of step are restored afterwards
+pub fn run_step(engine : &mut ContractInstance,index : usize,step : &Step) -> Result<StepResult,String>{
+    apply_setup(engine,step)?;
+    let mut result = StepResult{
+        step: index,
+        name: step.name.clone(),
+        call: None,
+        success: true,
+        data: Value::Null,
+        error: None,
+        gas_used: 0,
+        log: Vec::new(),
+        passed: true,
+        failures: Vec::new()
+    };
+    let (call_type,msg) = match step.call()? {
+        None => return Ok(result),
+        Some(c) => c
+    };
+    result.call = Some(call_type.to_string());
+    let sender = engine.sender.clone();
+    let funds = engine.env.message.sent_funds.clone();
+    let gas_limit = engine.gas_limit;
+    if let Some(s) = &step.sender {
+        engine.set_sender(s.as_str())?;
+    }
+    if let Some(f) = &step.funds {
+        engine.env.message.sent_funds = accounts::parse_coins(f.as_str())?;
+    }
+    if let Some(g) = step.gas_limit {
+        if g == 0 {
+            return Err("gas_limit of step must be more than 0".to_string());
+        }
+        engine.gas_limit = g;
+    }
+    match engine.execute(call_type,msg.as_str()) {
+        Ok(r) => {
+            result.data = data_value(r.data.as_slice());
+            result.gas_used = r.gas_used;
+            result.log = r.log.iter().map(|l| (l.key.clone(),l.value.clone())).collect();
+        },
+        Err(e) => {
+            result.success = false;
+            result.error = Some(e.to_string());
+        }
+    }
+    engine.set_sender(sender.as_str())?;
+    engine.env.message.sent_funds = funds;
+    engine.gas_limit = gas_limit;
+    //`"@alias"` in expectations is expanded like in messages
+    let expect : Expect = match &step.expect {
+        None => Expect::default(),
+        Some(e) => {
+            let text = serde_json::to_string(e).map_err(|e| e.to_string())?;
+            serde_json::from_str(engine.accounts.expand(text.as_str())?.as_str()).map_err(|e| e.to_string())?
+        }
+    };
+    result.failures = expect.check(&engine.accounts,&result.data,&result.error,result.gas_used,&result.log,&engine.storage.snapshot());
+    result.passed = result.failures.is_empty();
+    return Ok(result);
+}
+
+fn print_result(accounts : &accounts::Accounts,result : &StepResult){
+    if watcher::is_json_output() {
+        return watcher::logger_report("step",serde_json::to_value(result).unwrap_or_default());
+    }
+    let title = match &result.name {
+        None => format!("step {}",result.step),
+        Some(n) => format!("step {} [{}]",result.step,n)
+    };
+    let call = match &result.call {
+        None => return println!("{:<24} setup",title),
+        Some(c) => c
+    };
+    let verdict = if result.passed { "PASS" } else { "FAIL" };
+    match &result.error {
+        Some(e) => println!("{:<24} {:<8} {}   error {}",title,call,verdict,accounts::label(accounts,e.as_str())),
+        None => println!("{:<24} {:<8} {}   gas {} data {}",title,call,verdict,result.gas_used,accounts::label(accounts,result.data.to_string().as_str()))
+    }
+    for f in &result.failures {
+        println!("\t{}",f);
+    }
+}
+
+//run every step in order, a broken step stops the run
+pub fn run_scenario(engine : &mut ContractInstance,scenario : &Scenario) -> Result<Vec<StepResult>,String>{
+    let mut results = Vec::new();
+    for (idx,step) in scenario.steps.iter().enumerate() {
+        let result = match run_step(engine,idx + 1,step) {
+            Err(e) => return Err(format!("step {} : {}",idx + 1,e)),
+            Ok(r) => r
+        };
+        print_result(&engine.accounts,&result);
+        results.push(result);
+    }
+    return Ok(results);
+}
+
+//summary of run, true if every step passed
+pub fn print_summary(results : &Vec<StepResult>) -> bool{
+    let calls : Vec<&StepResult> = results.iter().filter(|r| r.call.is_some()).collect();
+    let failed : Vec<&StepResult> = calls.iter().filter(|r| !r.passed).cloned().collect();
+    if watcher::is_json_output() {
+        let steps : Vec<usize> = failed.iter().map(|r| r.step).collect();
+        watcher::logger_report("summary",serde_json::json!({"calls": calls.len(), "passed": calls.len() - failed.len(), "failed": steps}));
+        return failed.is_empty();
+    }
+    println!("=============================== scenario summary ===============================");
+    println!("{} calls, {} passed, {} failed",calls.len(),calls.len() - failed.len(),failed.len());
+    for r in &failed {
+        println!("failed step {}{}",r.step,r.name.as_ref().map(|n| format!(" [{}]",n)).unwrap_or_default());
+    }
+    return failed.is_empty();
+}
+
+pub fn save_results(results : &Vec<StepResult>,file : &str) -> Result<(),String>{
+    let text = serde_json::to_string_pretty(results).map_err(|e| e.to_string())?;
+    return std::fs::write(file,text).map_err(|e| format!("can not write results {}, {}",file,e));
+}
+
+//records setup and every call of an interactive session as a scenario, saved after each step
+pub struct Recorder {
+    file : String,
+    scenario : Scenario,
+}
+
+impl Recorder {
+    pub fn new(file : &str,settings : &Settings) -> Result<Self,String>{
+        let recorder = Recorder{
+            file: file.to_string(),
+            scenario: Scenario{
+                setup: Some(Config::from_settings(settings)),
+                steps: Vec::new()
+            }
+        };
+        recorder.save()?;
+        watcher::logger_info(&format!("recording session to {}",file));
+        return Ok(recorder);
+    }
+
+    fn save(&self) -> Result<(),String>{
+        return save_scenario(&self.scenario,self.file.as_str());
+    }
+
+    fn push(&mut self,step : Step){
+        self.scenario.steps.push(step);
+        if let Err(e) = self.save() {
+            watcher::logger_info(e.as_str());
+        }
+    }
+
+    //number of steps recorded so far
+    pub fn step_count(&self) -> usize{
+        return self.scenario.steps.len();
+    }
+
+    //drop steps after the first len, like when the session is reset or undone
+    pub fn truncate(&mut self,len : usize){
+        if len >= self.scenario.steps.len() {
+            return;
+        }
+        self.scenario.steps.truncate(len);
+        if let Err(e) = self.save() {
+            watcher::logger_info(e.as_str());
+        }
+    }
+
+    pub fn record_balance(&mut self,who : &HumanAddr,coins : &[Coin]){
+        let mut balance = BTreeMap::new();
+        balance.insert(who.to_string(),accounts::format_coins(coins));
+        self.push(Step{
+            balance: Some(balance),
+            ..Step::default()
+        });
+    }
+
+    pub fn record_block(&mut self,blocks : u64){
+        self.push(Step{
+            block: Some(blocks),
+            ..Step::default()
+        });
+    }
+
+    //message is recorded as typed, the outcome becomes the expectation of replay
+    pub fn record_call(&mut self,env : &Env,sender : &HumanAddr,gas_limit : u64,call_type : &str,msg : &str,result : &Result<CallResult,CallError>){
+        //json is kept as a value only if it is written back the same, anything else is the raw text so replay sends the same bytes
+        let message = match serde_json::from_str::<Value>(msg){
+            Ok(v) if !v.is_string() && serde_json::to_string(&v).ok().as_deref() == Some(msg) => v,
+            _ => Value::String(msg.to_string())
+        };
+        let expect = match result {
+            Ok(r) => Expect{
+                data: Some(data_value(r.data.as_slice())),
+                ..Expect::default()
+            },
+            Err(e) => Expect{
+                error: Some(e.to_string()),
+                ..Expect::default()
+            }
+        };
+        let mut step = Step{
+            sender: Some(sender.to_string()),
+            funds: Some(accounts::format_coins(&env.message.sent_funds)),
+            gas_limit: Some(gas_limit),
+            env: Some(EnvStep{
+                height: Some(env.block.height),
+                time: Some(env.block.time),
+                chain_id: Some(env.block.chain_id.clone())
+            }),
+            expect: Some(expect),
+            ..Step::default()
+        };
+        match call_type {
+            "init" => step.init = Some(message),
+            "handle" => step.handle = Some(message),
+            _ => step.query = Some(message)
+        }
+        self.push(step);
+    }
+}
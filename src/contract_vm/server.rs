@@ -0,0 +1,66 @@
+//websocket server, every text message is a scenario step run against one shared contract instance
+
+use websocket::OwnedMessage;
+use websocket::sync::Server;
+use crate::contract_vm::engine::ContractInstance;
+use crate::contract_vm::scenario::{self, Step};
+use crate::contract_vm::watcher;
+
+//reply of one request, the step result or the reason it could not run
+fn handle_request(engine : &mut ContractInstance,index : usize,text : &str) -> String{
+    let step : Step = match serde_json::from_str(text){
+        Err(e) => return serde_json::json!({"error": format!("request is not a step, {}",e)}).to_string(),
+        Ok(s) => s
+    };
+    return match scenario::run_step(engine,index,&step) {
+        Err(e) => serde_json::json!({"error": e}).to_string(),
+        Ok(r) => serde_json::to_string(&r).unwrap_or_default()
+    };
+}
+
+//clients are served one after another, the contract instance can not be shared between threads
+pub fn serve(engine : &mut ContractInstance,address : &str) -> Result<(),String>{
+    let mut server = match Server::bind(address){
+        Err(e) => return Err(format!("can not listen on {}, {}",address,e)),
+        Ok(s) => s
+    };
+    watcher::logger_info(&format!("serving on ws://{}",address));
+    let mut index = 0;
+    loop {
+        let upgrade = match server.accept() {
+            Err(_e) => continue,
+            Ok(u) => u
+        };
+        let mut client = match upgrade.accept() {
+            Err((_stream,e)) => {
+                watcher::logger_info(&format!("websocket handshake failed, {}",e));
+                continue;
+            },
+            Ok(c) => c
+        };
+        let peer = client.peer_addr().map(|p| p.to_string()).unwrap_or_default();
+        watcher::logger_info(&format!("client {} connected",peer));
+        loop {
+            let message = match client.recv_message() {
+                Err(_e) => break,
+                Ok(m) => m
+            };
+            let reply = match message {
+                OwnedMessage::Text(text) => {
+                    index += 1;
+                    OwnedMessage::Text(handle_request(engine,index,text.as_str()))
+                },
+                OwnedMessage::Ping(data) => OwnedMessage::Pong(data),
+                OwnedMessage::Close(_) => {
+                    let _ = client.send_message(&OwnedMessage::Close(None));
+                    break;
+                },
+                _ => continue
+            };
+            if client.send_message(&reply).is_err() {
+                break;
+            }
+        }
+        watcher::logger_info(&format!("client {} disconnected",peer));
+    }
+}
@@ -0,0 +1,245 @@
+//colon commands of interactive session, typed at call type prompt
+
+use std::path::Path;
+use crate::contract_vm::engine::ContractInstance;
+use crate::contract_vm::{accounts, console, scenario, watcher};
+
+pub enum CommandResult {
+    //input is a call type, not a command
+    NotCommand,
+    Done,
+    Quit,
+}
+
+//command, arguments and description shown by :help
+static COMMANDS: [(&str,&str,&str); 15] = [
+    (":env","[height|time|chain_id <value>]","show env, or change block height, time or chain id"),
+    (":sender","[account|address]","show or change sender"),
+    (":funds","[coins]","show or change funds attached to init and handle, like 100okt,5uatom"),
+    (":balance","<account|address> [coins]","show or set bank balance"),
+    (":balances","","show all bank balances"),
+    (":delegations","","show staking delegations and rewards"),
+    (":block","[n]","move chain forward n blocks, 1 if not set"),
+    (":state","","show contract storage"),
+    (":schema","[message]","show message types, or json schema of one message"),
+    (":gas","[limit]","show or change gas limit of every call"),
+    (":undo","","undo the latest init or handle, repeat to go further back"),
+    (":reset","","reset storage, balances, staking and block to the state after setup"),
+    (":load","<scenario file|schema dir>","run steps of scenario in this session, or load json schema from directory"),
+    (":help","","show this help"),
+    (":quit","","leave session"),
+];
+
+//words offered by tab completion
+pub fn completion_words(engine : &ContractInstance) -> Vec<String>{
+    let mut words : Vec<String> = vec!["init".to_string(),"handle".to_string(),"query".to_string()];
+    words.extend(COMMANDS.iter().map(|c| c.0.to_string()));
+    for (title,variants) in &engine.analyzer.map_of_member {
+        words.push(title.clone());
+        for (variant,members) in variants {
+            words.push(variant.clone());
+            words.extend(members.iter().map(|m| m.member_name.clone()));
+        }
+    }
+    for fields in engine.analyzer.map_of_struct.values() {
+        words.extend(fields.keys().cloned());
+    }
+    for account in engine.accounts.all() {
+        words.push(format!("@{}",account.alias));
+    }
+    return words;
+}
+
+fn show_help(){
+    for (command,args,description) in COMMANDS.iter() {
+        watcher::logger_info(&format!("{:<40} {}",format!("{} {}",command,args),description));
+    }
+}
+
+fn show_env(engine : &ContractInstance){
+    watcher::logger_info(&format!("height   : {}",engine.env.block.height));
+    watcher::logger_info(&format!("time     : {}",engine.env.block.time));
+    watcher::logger_info(&format!("chain_id : {}",engine.env.block.chain_id));
+    watcher::logger_info(&format!("sender   : {}",accounts::label(&engine.accounts,engine.sender.as_str())));
+    watcher::logger_info(&format!("funds    : [{}]",accounts::format_coins(&engine.env.message.sent_funds)));
+    watcher::logger_info(&format!("contract : {}",engine.contract_addr));
+}
+
+fn change_env(engine : &mut ContractInstance,field : &str,value : &str) -> Result<(),String>{
+    match field {
+        "height" => engine.env.block.height = value.parse::<u64>().map_err(|_e| format!("wrong height {}",value))?,
+        "time" => engine.env.block.time = value.parse::<u64>().map_err(|_e| format!("wrong time {}",value))?,
+        "chain_id" => engine.env.block.chain_id = value.to_string(),
+        _ => return Err(format!("unknown env field {}, must be one of height, time, chain_id",field))
+    }
+    return Ok(());
+}
+
+fn show_state(engine : &ContractInstance){
+    let storage = engine.storage.snapshot();
+    if storage.is_empty() {
+        watcher::logger_info("storage is empty");
+    }
+    for (key,value) in storage {
+        watcher::logger_info(&format!("{} = {}",accounts::label(&engine.accounts,watcher::printable(key.as_slice()).as_str()),accounts::label(&engine.accounts,watcher::printable(value.as_slice()).as_str())));
+    }
+}
+
+fn show_schema(engine : &ContractInstance,title : Option<&str>){
+    match title {
+        None => {
+            engine.analyzer.dump_all_roles();
+            engine.analyzer.dump_all_members();
+        },
+        Some(t) => match engine.analyzer.map_of_schema.get(t) {
+            None => watcher::logger_info(&format!("no schema of {}",t)),
+            Some(schema) => watcher::logger_info(serde_json::to_string_pretty(schema).unwrap_or_default().as_str())
+        }
+    }
+}
+
+fn load(engine : &mut ContractInstance,path : &str) -> Result<(),String>{
+    if Path::new(path).is_dir() {
+        let count = engine.analyzer.try_load_json_schema(Path::new(path))?;
+        watcher::logger_info(&format!("{} schema files loaded",count));
+        console::set_words(completion_words(engine));
+        return Ok(());
+    }
+    let steps = scenario::load_scenario(path)?;
+    if steps.setup.is_some() {
+        watcher::logger_info(&format!("setup of {} is skipped, the session keeps its own",path));
+    }
+    let results = scenario::run_scenario(engine,&steps)?;
+    scenario::print_summary(&results);
+    return Ok(());
+}
+
+//run colon command typed at call type prompt
+pub fn run_command(engine : &mut ContractInstance,line : &str) -> CommandResult{
+    let words : Vec<&str> = line.split_whitespace().collect();
+    if words.is_empty() || !words[0].starts_with(':') {
+        return CommandResult::NotCommand;
+    }
+    match words[0] {
+        ":quit" | ":q" | ":exit" => return CommandResult::Quit,
+        ":help" => show_help(),
+        ":env" => {
+            if words.len() == 3 {
+                if let Err(e) = change_env(engine,words[1],words[2]) {
+                    watcher::logger_info(e.as_str());
+                    return CommandResult::Done;
+                }
+            }else if words.len() != 1 {
+                watcher::logger_info("usage :env [height|time|chain_id <value>]");
+                return CommandResult::Done;
+            }
+            show_env(engine);
+        },
+        ":sender" => {
+            if words.len() < 2 {
+                watcher::logger_info(&format!("sender is {}",accounts::label(&engine.accounts,engine.sender.as_str())));
+                return CommandResult::Done;
+            }
+            match engine.set_sender(words[1]) {
+                Ok(human) => watcher::logger_info(&format!("sender is {} now",human)),
+                Err(e) => watcher::logger_info(e.as_str())
+            }
+        },
+        ":balance" => {
+            if words.len() < 2 {
+                watcher::logger_info("usage :balance <account|address> [coins like 100okt,5uatom]");
+                return CommandResult::Done;
+            }
+            let human = engine.accounts.resolve(words[1]);
+            if words.len() > 2 {
+                match accounts::parse_coins(words[2]) {
+                    Ok(coins) => {
+                        if let Some(recorder) = &mut engine.recorder {
+                            recorder.record_balance(&human,&coins);
+                        }
+                        engine.bank.set_balance(&human,coins)
+                    },
+                    Err(e) => {
+                        watcher::logger_info(e.as_str());
+                        return CommandResult::Done;
+                    }
+                }
+            }
+            watcher::logger_info(&format!("{} : {}",accounts::label(&engine.accounts,human.as_str()),accounts::format_coins(&engine.bank.all_balances(&human))));
+        },
+        ":funds" => {
+            if words.len() > 1 {
+                match accounts::parse_coins(words[1]) {
+                    Ok(coins) => engine.env.message.sent_funds = coins,
+                    Err(e) => {
+                        watcher::logger_info(e.as_str());
+                        return CommandResult::Done;
+                    }
+                }
+            }
+            watcher::logger_info(&format!("funds attached to init and handle : [{}]",accounts::format_coins(&engine.env.message.sent_funds)));
+        },
+        ":balances" => {
+            for (address,coins) in engine.bank.all() {
+                watcher::logger_info(&format!("{} : {}",accounts::label(&engine.accounts,address.as_str()),accounts::format_coins(&coins)));
+            }
+        },
+        ":block" => {
+            let blocks = match words.get(1) {
+                None => 1,
+                Some(n) => match n.parse::<u64>() {
+                    Ok(b) => b,
+                    Err(_e) => {
+                        watcher::logger_info("usage :block [number of blocks]");
+                        return CommandResult::Done;
+                    }
+                }
+            };
+            engine.advance_blocks(blocks);
+            watcher::logger_info(&format!("block height {} time {}",engine.env.block.height,engine.env.block.time));
+        },
+        ":delegations" => {
+            for d in engine.staking.delegations() {
+                watcher::logger_info(&format!("{} => {} : {} rewards {}",accounts::label(&engine.accounts,d.delegator.as_str()),
+                         accounts::label(&engine.accounts,d.validator.as_str()),d.amount,d.rewards));
+            }
+        },
+        ":state" => show_state(engine),
+        ":schema" => show_schema(engine,words.get(1).cloned()),
+        ":gas" => {
+            if let Some(limit) = words.get(1) {
+                match limit.parse::<u64>() {
+                    Ok(g) if g > 0 => engine.gas_limit = g,
+                    _ => {
+                        watcher::logger_info(&format!("wrong gas limit {}",limit));
+                        return CommandResult::Done;
+                    }
+                }
+            }
+            watcher::logger_info(&format!("gas limit is {}",engine.gas_limit));
+        },
+        ":undo" => {
+            if engine.undo() {
+                watcher::logger_info(&format!("undone, block height {} time {}, {} more to undo",engine.env.block.height,engine.env.block.time,engine.undo_stack.len()));
+            }else {
+                watcher::logger_info("nothing to undo");
+            }
+        },
+        ":reset" => {
+            engine.reset();
+            watcher::logger_info("session reset to the state after setup");
+        },
+        ":load" => {
+            match words.get(1) {
+                None => watcher::logger_info("usage :load <scenario file|schema dir>"),
+                Some(path) => {
+                    if let Err(e) = load(engine,path) {
+                        watcher::logger_info(e.as_str());
+                    }
+                }
+            }
+        },
+        _ => watcher::logger_info(&format!("unknown command {}, :help shows all commands",words[0]))
+    }
+    return CommandResult::Done;
+}
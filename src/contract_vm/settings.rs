@@ -0,0 +1,128 @@
+//settings of simulation, set from config file and command line
+use cosmwasm_std::Coin;
+use crate::contract_vm::{mock, accounts, engine};
+
+pub static DEFAULT_CANONICAL_LENGTH: usize = 20;
+
+pub struct Settings {
+    //bech32 human readable prefix like `cosmos`, raw addresses are used if not set
+    pub address_prefix : Option<String>,
+    //length of canonical address, 20 bytes like wasmd if not set
+    pub canonical_length : usize,
+    //alias, address (generated if not set) and starting balance of named accounts
+    pub accounts : Vec<(String,Option<String>,Vec<Coin>)>,
+    //alias or address of default sender
+    pub sender : Option<String>,
+    //account alias or address with its bank balance
+    pub balances : Vec<(String,Vec<Coin>)>,
+    //funds attached to every init and handle
+    pub funds : Vec<Coin>,
+    //validator alias or address with commission percent
+    pub validators : Vec<(String,u64)>,
+    //delegator, validator and bonded amount of starting delegations
+    pub delegations : Vec<(String,String,u128)>,
+    pub bonded_denom : Option<String>,
+    //staking rewards per block in parts per million of delegated amount
+    pub reward_rate : Option<u128>,
+    //json fixture answering custom queries and messages
+    pub custom_fixture : Option<String>,
+    //command of external process answering custom queries and messages
+    pub custom_process : Option<String>,
+    //json fixture answering wasm queries to contracts that are not loaded
+    pub contract_fixture : Option<String>,
+    //gas limit of every call
+    pub gas_limit : u64,
+    pub chain_id : String,
+    //block of env before the first call
+    pub block_height : u64,
+    pub block_time : u64,
+    //address of the simulated contract, generated if not set
+    pub contract_address : Option<String>,
+}
+
+impl Settings {
+    pub fn default() -> Self{
+        return Settings{
+            address_prefix: None,
+            canonical_length: DEFAULT_CANONICAL_LENGTH,
+            accounts: Vec::new(),
+            sender: None,
+            balances: Vec::new(),
+            funds: Vec::new(),
+            validators: Vec::new(),
+            delegations: Vec::new(),
+            bonded_denom: None,
+            reward_rate: None,
+            custom_fixture: None,
+            custom_process: None,
+            contract_fixture: None,
+            gas_limit: engine::DEFAULT_GAS_LIMIT,
+            chain_id: engine::DEFAULT_CHAIN_ID.to_string(),
+            block_height: 0,
+            block_time: 0,
+            contract_address: None
+        }
+    }
+
+    pub fn build_api(&self) -> Result<mock::MockApi,String>{
+        return match &self.address_prefix {
+            None => Ok(mock::MockApi::new(self.canonical_length)),
+            Some(prefix) => mock::MockApi::new_bech32(self.canonical_length,prefix.as_str())
+        };
+    }
+
+    //parse account like `alice`, `alice=ADDR`, `alice:100okt` or `alice=ADDR:100okt,5uatom`
+    pub fn add_account(&mut self,def : &str) -> Result<(),String>{
+        let (head,coins) = match def.find(':') {
+            None => (def,Vec::new()),
+            Some(idx) => (&def[..idx],accounts::parse_coins(&def[idx + 1..])?)
+        };
+        let (alias,address) = match head.find('=') {
+            None => (head,None),
+            Some(idx) => (&head[..idx],Some(head[idx + 1..].to_string()))
+        };
+        if alias.is_empty() {
+            return Err(format!("account [{}] has no alias",def));
+        }
+        self.accounts.push((alias.to_string(),address,coins));
+        return Ok(());
+    }
+
+    //parse balance like `alice=100okt,5uatom` or `ADDR=100okt`
+    pub fn add_balance(&mut self,def : &str) -> Result<(),String>{
+        let idx = match def.find('='){
+            None => return Err(format!("balance [{}] must be like alice=100okt",def)),
+            Some(i) => i
+        };
+        let coins = accounts::parse_coins(&def[idx + 1..])?;
+        self.balances.push((def[..idx].to_string(),coins));
+        return Ok(());
+    }
+
+    //parse validator like `val1` or `val1:5` with commission percent
+    pub fn add_validator(&mut self,def : &str) -> Result<(),String>{
+        let (name,commission) = match def.find(':') {
+            None => (def,0),
+            Some(idx) => match def[idx + 1..].parse::<u64>() {
+                Err(_e) => return Err(format!("validator [{}] has wrong commission",def)),
+                Ok(c) => (&def[..idx],c)
+            }
+        };
+        self.validators.push((name.to_string(),commission));
+        return Ok(());
+    }
+
+    //parse delegation like `alice:val1:1000`, amount is in bonded denom
+    pub fn add_delegation(&mut self,def : &str) -> Result<(),String>{
+        let parts : Vec<&str> = def.split(':').collect();
+        if parts.len() != 3 {
+            return Err(format!("delegation [{}] must be like alice:val1:1000",def));
+        }
+        let amount = match parts[2].parse::<u128>(){
+            Err(_e) => return Err(format!("delegation [{}] has wrong amount",def)),
+            Ok(a) => a
+        };
+        self.delegations.push((parts[0].to_string(),parts[1].to_string(),amount));
+        return Ok(());
+    }
+}
@@ -0,0 +1,269 @@
+//staking module of simulation, validators and delegations shared by engine and querier
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use cosmwasm_std::{to_binary, AllDelegationsResponse, BondedDenomResponse, Coin, Decimal, Delegation, DelegationResponse,
+                   FullDelegation, HumanAddr, QuerierResult, StakingMsg, StakingQuery, SystemError, Uint128, Validator,
+                   ValidatorsResponse};
+use crate::contract_vm::bank::Bank;
+
+//bonded coins are held by this address while delegated
+pub static BONDED_POOL: &str = "bonded_pool";
+static DEFAULT_BONDED_DENOM: &str = "stake";
+//rewards accrued per block, in parts per million of delegated amount
+static DEFAULT_REWARD_RATE: u128 = 100;
+static MAX_COMMISSION_PERCENT: u64 = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelegationState {
+    pub delegator : HumanAddr,
+    pub validator : HumanAddr,
+    pub amount : u128,
+    pub rewards : u128,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StakingState {
+    pub bonded_denom : String,
+    pub reward_rate : u128,
+    pub validators : Vec<Validator>,
+    pub delegations : Vec<DelegationState>,
+}
+
+#[derive(Clone)]
+pub struct Staking {
+    state : Rc<RefCell<StakingState>>,
+}
+
+impl Default for Staking {
+    fn default() -> Self{
+        return Staking{
+            state: Rc::new(RefCell::new(StakingState{
+                bonded_denom: DEFAULT_BONDED_DENOM.to_string(),
+                reward_rate: DEFAULT_REWARD_RATE,
+                validators: Vec::new(),
+                delegations: Vec::new()
+            }))
+        };
+    }
+}
+
+impl Staking {
+    pub fn new() -> Self{
+        return Staking::default();
+    }
+
+    pub fn snapshot(&self) -> StakingState{
+        return self.state.borrow().clone();
+    }
+
+    pub fn restore(&self,state : StakingState){
+        *self.state.borrow_mut() = state;
+    }
+
+    pub fn set_bonded_denom(&self,denom : &str){
+        self.state.borrow_mut().bonded_denom = denom.to_string();
+    }
+
+    pub fn bonded_denom(&self) -> String{
+        return self.state.borrow().bonded_denom.clone();
+    }
+
+    pub fn set_reward_rate(&self,rate : u128){
+        self.state.borrow_mut().reward_rate = rate;
+    }
+
+    pub fn add_validator(&self,address : &HumanAddr,commission_percent : u64) -> Result<(),String>{
+        if commission_percent > MAX_COMMISSION_PERCENT {
+            return Err(format!("commission of validator {} is {}%, can not be more than {}%",address,commission_percent,MAX_COMMISSION_PERCENT));
+        }
+        let mut state = self.state.borrow_mut();
+        state.validators.retain(|v| v.address != *address);
+        state.validators.push(Validator{
+            address: address.clone(),
+            commission: Decimal::percent(commission_percent),
+            max_commission: Decimal::percent(MAX_COMMISSION_PERCENT),
+            max_change_rate: Decimal::percent(1)
+        });
+        return Ok(());
+    }
+
+    fn has_validator(&self,address : &HumanAddr) -> bool{
+        return self.state.borrow().validators.iter().any(|v| v.address == *address);
+    }
+
+    //starting delegation set by settings, bonded coins are not taken from the delegator
+    //but minted to the bonded pool, so they can be undelegated
+    pub fn add_delegation(&self,bank : &Bank,delegator : &HumanAddr,validator : &HumanAddr,amount : u128) -> Result<(),String>{
+        if !self.has_validator(validator) {
+            return Err(format!("validator {} not found",validator));
+        }
+        bank.mint(&HumanAddr::from(BONDED_POOL),&Coin{
+            denom: self.bonded_denom(),
+            amount: Uint128(amount)
+        });
+        self.bond(delegator,validator,amount);
+        return Ok(());
+    }
+
+    pub fn delegations(&self) -> Vec<DelegationState>{
+        return self.state.borrow().delegations.clone();
+    }
+
+    fn bond(&self,delegator : &HumanAddr,validator : &HumanAddr,amount : u128){
+        let mut state = self.state.borrow_mut();
+        match state.delegations.iter_mut().find(|d| d.delegator == *delegator && d.validator == *validator) {
+            Some(d) => d.amount = d.amount.saturating_add(amount),
+            None => state.delegations.push(DelegationState{
+                delegator: delegator.clone(),
+                validator: validator.clone(),
+                amount,
+                rewards: 0
+            })
+        }
+    }
+
+    fn unbond(&self,delegator : &HumanAddr,validator : &HumanAddr,amount : u128) -> Result<(),String>{
+        let mut state = self.state.borrow_mut();
+        let d = match state.delegations.iter_mut().find(|d| d.delegator == *delegator && d.validator == *validator) {
+            None => return Err(format!("{} has no delegation to {}",delegator,validator)),
+            Some(d) => d
+        };
+        if d.amount < amount {
+            return Err(format!("{} delegated only {} to {}, can not undelegate {}",delegator,d.amount,validator,amount));
+        }
+        d.amount -= amount;
+        return Ok(());
+    }
+
+    fn take_rewards(&self,delegator : &HumanAddr,validator : &HumanAddr) -> u128{
+        let mut state = self.state.borrow_mut();
+        return match state.delegations.iter_mut().find(|d| d.delegator == *delegator && d.validator == *validator) {
+            None => 0,
+            Some(d) => {
+                let rewards = d.rewards;
+                d.rewards = 0;
+                rewards
+            }
+        };
+    }
+
+    //accrue simple rewards of every delegation for passed blocks
+    //rewards stop growing at u128::MAX instead of overflowing on huge amounts, rates or block counts
+    pub fn accrue(&self,blocks : u64){
+        let mut state = self.state.borrow_mut();
+        let rate = state.reward_rate;
+        for d in state.delegations.iter_mut() {
+            let earned = match d.amount.checked_mul(rate).and_then(|r| r.checked_mul(blocks as u128)) {
+                Some(r) => r / 1_000_000,
+                //divide first, losing only the fraction of a coin per million
+                None => (d.amount / 1_000_000).saturating_mul(rate).saturating_mul(blocks as u128)
+            };
+            d.rewards = d.rewards.saturating_add(earned);
+        }
+    }
+
+    fn check_amount(&self,amount : &Coin) -> Result<u128,String>{
+        let denom = self.bonded_denom();
+        if amount.denom != denom {
+            return Err(format!("can only stake {}, got {}",denom,amount.denom));
+        }
+        return Ok(amount.amount.u128());
+    }
+
+    //execute staking message sent by delegator, bonded coins move between delegator and bonded pool
+    pub fn execute(&self,bank : &Bank,delegator : &HumanAddr,msg : &StakingMsg) -> Result<(),String>{
+        let pool = HumanAddr::from(BONDED_POOL);
+        match msg {
+            StakingMsg::Delegate{validator,amount} => {
+                let value = self.check_amount(amount)?;
+                if !self.has_validator(validator) {
+                    return Err(format!("validator {} not found",validator));
+                }
+                bank.transfer(delegator,&pool,&[amount.clone()])?;
+                self.bond(delegator,validator,value);
+            },
+            StakingMsg::Undelegate{validator,amount} => {
+                let value = self.check_amount(amount)?;
+                //no unbonding period in simulation, coins return at once
+                self.unbond(delegator,validator,value)?;
+                bank.transfer(&pool,delegator,&[amount.clone()])?;
+            },
+            StakingMsg::Redelegate{src_validator,dst_validator,amount} => {
+                let value = self.check_amount(amount)?;
+                if !self.has_validator(dst_validator) {
+                    return Err(format!("validator {} not found",dst_validator));
+                }
+                self.unbond(delegator,src_validator,value)?;
+                self.bond(delegator,dst_validator,value);
+            },
+            StakingMsg::Withdraw{validator,recipient} => {
+                let rewards = self.take_rewards(delegator,validator);
+                let to = recipient.clone().unwrap_or_else(|| delegator.clone());
+                //rewards are minted, nobody pays them in simulation
+                bank.mint(&to,&Coin{
+                    denom: self.bonded_denom(),
+                    amount: Uint128(rewards)
+                });
+            }
+        }
+        return Ok(());
+    }
+
+    fn full_delegation(&self,d : &DelegationState) -> FullDelegation{
+        let denom = self.bonded_denom();
+        return FullDelegation{
+            delegator: d.delegator.clone(),
+            validator: d.validator.clone(),
+            amount: Coin{
+                denom: denom.clone(),
+                amount: Uint128(d.amount)
+            },
+            can_redelegate: Coin{
+                denom: denom.clone(),
+                amount: Uint128(d.amount)
+            },
+            accumulated_rewards: Coin{
+                denom,
+                amount: Uint128(d.rewards)
+            }
+        };
+    }
+
+    pub fn query(&self,request : &StakingQuery) -> QuerierResult{
+        let state = self.snapshot();
+        let bin = match request {
+            StakingQuery::BondedDenom{} => to_binary(&BondedDenomResponse{
+                denom: state.bonded_denom.clone()
+            }),
+            StakingQuery::Validators{} => to_binary(&ValidatorsResponse{
+                validators: state.validators.clone()
+            }),
+            StakingQuery::AllDelegations{delegator} => {
+                let delegations = state.delegations.iter()
+                    .filter(|d| d.delegator == *delegator && d.amount > 0)
+                    .map(|d| Delegation{
+                        delegator: d.delegator.clone(),
+                        validator: d.validator.clone(),
+                        amount: Coin{
+                            denom: state.bonded_denom.clone(),
+                            amount: Uint128(d.amount)
+                        }
+                    }).collect();
+                to_binary(&AllDelegationsResponse{ delegations })
+            },
+            StakingQuery::Delegation{delegator,validator} => {
+                let delegation = state.delegations.iter()
+                    .find(|d| d.delegator == *delegator && d.validator == *validator && d.amount > 0)
+                    .map(|d| self.full_delegation(d));
+                to_binary(&DelegationResponse{ delegation })
+            }
+        };
+        return match bin {
+            Ok(b) => Ok(Ok(b)),
+            Err(e) => Err(SystemError::InvalidResponse{
+                error: e.to_string()
+            })
+        };
+    }
+}
@@ -0,0 +1,172 @@
+//template generator, build skeleton json message for every message variant found in schema
+
+use serde_json::{Value, Map};
+use std::io::Write;
+use crate::contract_vm::analyzer::Analyzer;
+use crate::contract_vm::{validator, watcher};
+
+static MAX_DEPTH: usize = 8;
+
+fn short_name(reference : &str) -> &str{
+    let seg = match reference.rfind('/') {
+        None => return reference,
+        Some(idx) => idx,
+    };
+    let (_,name) = reference.split_at(seg + 1);
+    return name;
+}
+
+fn placeholder_of_definition(name : &str) -> Option<Value>{
+    return match name {
+        "Uint128" | "Decimal" => Some(Value::String("0".to_string())),
+        "HumanAddr" => Some(Value::String("address".to_string())),
+        "Binary" => Some(Value::String("".to_string())),
+        _ => None
+    };
+}
+
+fn placeholder_of_type(root : &Value,schema : &Value,type_name : &str,depth : usize) -> Value{
+    return match type_name {
+        "string" => Value::String("string".to_string()),
+        "integer" | "number" => Value::from(0),
+        "boolean" => Value::Bool(false),
+        "array" => {
+            match schema.get("items") {
+                None => Value::Array(Vec::new()),
+                Some(items) => Value::Array(vec![placeholder(root,items,depth + 1)])
+            }
+        },
+        "object" => {
+            let mut obj = Map::new();
+            if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+                for (k,v) in props {
+                    obj.insert(k.clone(),placeholder(root,v,depth + 1));
+                }
+            }
+            Value::Object(obj)
+        },
+        _ => Value::Null
+    };
+}
+
+//type appropriate placeholder value of schema node
+pub fn placeholder(root : &Value,schema : &Value,depth : usize) -> Value{
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+    if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+        if let Some(v) = placeholder_of_definition(short_name(reference)) {
+            return v;
+        }
+        return match validator::resolve_ref(root,reference){
+            None => Value::Null,
+            Some(target) => placeholder(root,target,depth + 1)
+        };
+    }
+    if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+        if let Some(first) = all.first() {
+            return placeholder(root,first,depth + 1);
+        }
+    }
+    if let Some(any) = schema.get("anyOf").or(schema.get("oneOf")).and_then(|a| a.as_array()) {
+        //prefer a non null alternative, optional fields are `anyOf [T, null]`
+        for alt in any {
+            if alt.get("type") != Some(&Value::String("null".to_string())) {
+                return placeholder(root,alt,depth + 1);
+            }
+        }
+        return Value::Null;
+    }
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if let Some(first) = options.first() {
+            return first.clone();
+        }
+    }
+    return match schema.get("type"){
+        Some(Value::String(t)) => placeholder_of_type(root,schema,t.as_str(),depth),
+        Some(Value::Array(types)) => {
+            let t = types.iter().filter_map(|t| t.as_str()).find(|t| *t != "null").unwrap_or("null");
+            placeholder_of_type(root,schema,t,depth)
+        },
+        _ => Value::Null
+    };
+}
+
+//every variant of a message schema with its skeleton message
+pub fn message_variants(schema : &Value) -> Vec<(String,Value)>{
+    let mut variants = Vec::new();
+    let alternatives = match schema.get("anyOf").or(schema.get("oneOf")).and_then(|a| a.as_array()){
+        None => {
+            let title = schema.get("title").and_then(|t| t.as_str()).unwrap_or("msg");
+            variants.push((title.to_string(),placeholder(schema,schema,0)));
+            return variants;
+        },
+        Some(a) => a
+    };
+    for alt in alternatives {
+        //unit variant is a plain string like "increment"
+        if let Some(options) = alt.get("enum").and_then(|e| e.as_array()) {
+            for o in options {
+                if let Some(name) = o.as_str() {
+                    variants.push((name.to_string(),o.clone()));
+                }
+            }
+            continue;
+        }
+        let names = validator::variant_names(&vec![alt.clone()]);
+        let name = match names.first(){
+            None => continue,
+            Some(n) => n
+        };
+        let body = match alt.get("properties").and_then(|p| p.get(name.as_str())){
+            None => continue,
+            Some(b) => b
+        };
+        let mut msg = Map::new();
+        msg.insert(name.clone(),placeholder(schema,body,0));
+        variants.push((name.clone(),Value::Object(msg)));
+    }
+    return variants;
+}
+
+//write templates of InitMsg, HandleMsg and QueryMsg into dir, or stdout if dir is "-"
+pub fn write_templates(analyzer : &Analyzer,dir : &str) -> Result<usize,String>{
+    let mut count = 0;
+    if dir != "-" {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            return Err(format!("failed to create directory {}, error: {}",dir,e));
+        }
+    }
+    for call_type in &["init","handle","query"] {
+        let schema = match analyzer.message_title_of(call_type).and_then(|t| analyzer.map_of_schema.get(&t)){
+            None => continue,
+            Some(s) => s
+        };
+        for (name,msg) in message_variants(schema) {
+            let file_name = if schema.get("anyOf").is_some() || schema.get("oneOf").is_some() {
+                format!("{}_{}.json",call_type,name)
+            }else {
+                format!("{}.json",call_type)
+            };
+            let content = serde_json::to_string_pretty(&msg).unwrap_or_default();
+            if dir == "-" && watcher::is_json_output() {
+                watcher::logger_report("template",serde_json::json!({"file": file_name, "message": msg}));
+            }else if dir == "-" {
+                println!("==> {} <==",file_name);
+                println!("{}",content);
+            }else {
+                let path = std::path::Path::new(dir).join(&file_name);
+                let mut file = match std::fs::File::create(&path){
+                    Err(e) => return Err(format!("failed to create file {}, error: {}",path.display(),e)),
+                    Ok(f) => f
+                };
+                if let Err(e) = writeln!(file,"{}",content) {
+                    return Err(format!("failed to write file {}, error: {}",path.display(),e));
+                }
+                watcher::logger_info(&format!("template written to {}",path.display()));
+            }
+            count += 1;
+        }
+    }
+    return Ok(count);
+}
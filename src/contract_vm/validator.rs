@@ -0,0 +1,210 @@
+//validator for json message, check message against json schema before calling contract
+
+use serde_json::Value;
+
+fn type_of_value(value : &Value) -> &'static str{
+    return match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) => {
+            if n.is_i64() || n.is_u64() {
+                "integer"
+            }else {
+                "number"
+            }
+        },
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+}
+
+fn is_type_match(expect : &str,value : &Value) -> bool{
+    let actual = type_of_value(value);
+    if expect == actual {
+        return true;
+    }
+    //integer is also a number
+    return expect == "number" && actual == "integer";
+}
+
+fn child_path(path : &str,name : &str) -> String{
+    if path.is_empty() {
+        return name.to_string();
+    }
+    return format!("{}.{}",path,name);
+}
+
+pub fn resolve_ref<'a>(root : &'a Value,reference : &str) -> Option<&'a Value>{
+    if !reference.starts_with("#/") {
+        return None;
+    }
+    let mut current = root;
+    for seg in reference[2..].split('/') {
+        current = match current.get(seg){
+            None => return None,
+            Some(v) => v
+        };
+    }
+    return Some(current);
+}
+
+fn check_format(format : &str,value : &Value,path : &str,errors : &mut Vec<String>){
+    let max = match format {
+        "uint8" => u8::MAX as u64,
+        "uint16" => u16::MAX as u64,
+        "uint32" => u32::MAX as u64,
+        "uint64" => u64::MAX,
+        _ => return,
+    };
+    match value.as_u64() {
+        None => errors.push(format!("{}: expected {}, got {}",path,format,value)),
+        Some(n) => {
+            if n > max {
+                errors.push(format!("{}: value {} out of range for {}",path,n,format));
+            }
+        }
+    }
+}
+
+fn check_alternatives(root : &Value,alternatives : &Vec<Value>,value : &Value,path : &str,errors : &mut Vec<String>){
+    let mut best : Option<Vec<String>> = None;
+    for alt in alternatives {
+        let mut alt_errors = Vec::new();
+        check(root,alt,value,path,&mut alt_errors);
+        if alt_errors.is_empty() {
+            return;
+        }
+        //an alternative which requires the same single key as the value is the variant the user meant
+        if let (Some(obj),Some(req)) = (value.as_object(),alt.get("required").and_then(|r| r.as_array())) {
+            if obj.len() == 1 && req.len() == 1 && obj.contains_key(req[0].as_str().unwrap_or("")) {
+                errors.extend(alt_errors);
+                return;
+            }
+        }
+        best = match best {
+            Some(b) if b.len() <= alt_errors.len() => Some(b),
+            _ => Some(alt_errors)
+        };
+    }
+
+    //value is an object with one key, but no variant requires that key
+    let variants = variant_names(alternatives);
+    if let Some(obj) = value.as_object() {
+        if obj.len() == 1 && !variants.is_empty() {
+            let key = obj.keys().next().unwrap();
+            errors.push(format!("{}: unknown variant \"{}\", expected one of [{}]",path,key,variants.join(", ")));
+            return;
+        }
+    }
+    if let Some(b) = best {
+        errors.extend(b);
+    }
+}
+
+pub fn variant_names(alternatives : &Vec<Value>) -> Vec<String>{
+    let mut names = Vec::new();
+    for alt in alternatives {
+        let req = match alt.get("required").and_then(|r| r.as_array()){
+            None => continue,
+            Some(r) => r
+        };
+        if req.len() != 1 {
+            continue;
+        }
+        if let Some(n) = req[0].as_str() {
+            names.push(n.to_string());
+        }
+    }
+    return names;
+}
+
+fn check(root : &Value,schema : &Value,value : &Value,path : &str,errors : &mut Vec<String>){
+    if let Some(b) = schema.as_bool() {
+        if !b {
+            errors.push(format!("{}: no value allowed here",path));
+        }
+        return;
+    }
+    if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+        match resolve_ref(root,reference){
+            None => errors.push(format!("{}: can not resolve schema reference {}",path,reference)),
+            Some(target) => check(root,target,value,path,errors)
+        }
+        return;
+    }
+    if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+        for sub in all {
+            check(root,sub,value,path,errors);
+        }
+    }
+    if let Some(any) = schema.get("anyOf").or(schema.get("oneOf")).and_then(|a| a.as_array()) {
+        check_alternatives(root,any,value,path,errors);
+    }
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.contains(value) {
+            let names : Vec<String> = options.iter().map(|o| o.to_string()).collect();
+            errors.push(format!("{}: expected one of [{}], got {}",path,names.join(", "),value));
+            return;
+        }
+    }
+
+    let types : Vec<&str> = match schema.get("type"){
+        None => Vec::new(),
+        Some(Value::String(s)) => vec![s.as_str()],
+        Some(Value::Array(arr)) => arr.iter().filter_map(|t| t.as_str()).collect(),
+        Some(_) => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| is_type_match(t,value)) {
+        let actual = if value.is_number() { "number" } else { type_of_value(value) };
+        errors.push(format!("{}: expected {}, got {}",path,types.join(" or "),actual));
+        return;
+    }
+    if let Some(format) = schema.get("format").and_then(|f| f.as_str()) {
+        if value.is_number() {
+            check_format(format,value,path,errors);
+        }
+    }
+
+    match value {
+        Value::Object(obj) => {
+            if let Some(req) = schema.get("required").and_then(|r| r.as_array()) {
+                for r in req {
+                    let name = match r.as_str(){
+                        None => continue,
+                        Some(n) => n
+                    };
+                    if !obj.contains_key(name) {
+                        errors.push(format!("{}: missing required field",child_path(path,name)));
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            for (k,v) in obj {
+                match properties.and_then(|p| p.get(k)) {
+                    Some(sub) => check(root,sub,v,child_path(path,k).as_str(),errors),
+                    None => {
+                        if properties.is_some() && schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                            errors.push(format!("{}: unknown field",child_path(path,k)));
+                        }
+                    }
+                }
+            }
+        },
+        Value::Array(arr) => {
+            if let Some(items) = schema.get("items") {
+                for (i,item) in arr.iter().enumerate() {
+                    check(root,items,item,format!("{}[{}]",path,i).as_str(),errors);
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+//validate value against a whole schema file, every error is prefixed by path
+pub fn validate(schema : &Value,value : &Value,path : &str) -> Vec<String>{
+    let mut errors = Vec::new();
+    check(schema,schema,value,path,&mut errors);
+    return errors;
+}
@@ -1,39 +1,184 @@
 use std::fmt::Write;
+use std::io::Write as IoWrite;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde_json::{json, Value};
+use crate::contract_vm::accounts;
 
-pub fn logger_storage_event_insert(key: &[u8], value: &[u8]){
-    let mut key_str = match std::str::from_utf8(&key){
-        Ok(result) => result.to_string(),
-        _ => "".to_string()
-    };
-    if key_str.is_empty() {
-        for a in key.iter() {
-            write!(key_str, "{:02x}", a).expect("Not written");
+static QUIET: AtomicBool = AtomicBool::new(false);
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    //events are written here in json mode, stdout if not set
+    static ref SINK: Mutex<Option<std::fs::File>> = Mutex::new(None);
+}
+
+//silence storage events, used by batch runs like fuzzing
+pub fn set_quiet(quiet: bool){
+    QUIET.store(quiet,Ordering::Relaxed);
+}
+
+//write one json object per event instead of text, to file if set or stdout
+pub fn set_json_output(file: Option<&str>) -> Result<(),String>{
+    if let Some(f) = file {
+        let out = match std::fs::File::create(f){
+            Err(e) => return Err(format!("can not create output file {}, {}",f,e)),
+            Ok(o) => o
+        };
+        if let Ok(mut sink) = SINK.lock() {
+            *sink = Some(out);
         }
     }
+    JSON_OUTPUT.store(true,Ordering::Relaxed);
+    return Ok(());
+}
+
+pub fn is_json_output() -> bool{
+    return JSON_OUTPUT.load(Ordering::Relaxed);
+}
+
+fn emit(event: Value){
+    let line = event.to_string();
+    match SINK.lock() {
+        Ok(mut sink) => match &mut *sink {
+            Some(f) => {
+                let _ = writeln!(f,"{}",line);
+            },
+            None => println!("{}",line)
+        },
+        Err(_e) => println!("{}",line)
+    }
+}
 
-    let mut val_str = match std::str::from_utf8(&value){
+//progress and report text, on stderr in json mode so stdout only carries events
+pub fn logger_info(text: &str){
+    if is_json_output() {
+        eprintln!("{}",text);
+        return;
+    }
+    println!("{}",text);
+}
+
+//report of batch runs like scenario steps or fuzz findings, one json event in json mode
+pub fn logger_report(event: &str, mut report: Value){
+    if let Value::Object(fields) = &mut report {
+        fields.insert("event".to_string(),Value::String(event.to_string()));
+    }
+    emit(report);
+}
+
+//utf8 text of bytes, hex if not printable
+pub fn printable(bytes: &[u8]) -> String{
+    let mut text = match std::str::from_utf8(bytes){
         Ok(result) => result.to_string(),
         _ => "".to_string()
     };
-    if val_str.is_empty() {
-        for a in key.iter() {
-            write!(val_str, "{:02x}", a).expect("Not written");
+    if text.is_empty() {
+        for a in bytes.iter() {
+            write!(text, "{:02x}", a).expect("Not written");
         }
     }
-
-    println!("DB Changed : [Insert]\nKey        : [{}]\nValue      : [{}]",key_str,val_str);
+    return text;
 }
 
-pub fn logger_storage_event_remove(key: &[u8]){
-    let mut key_str = match std::str::from_utf8(&key){
-        Ok(result) => result.to_string(),
-        _ => "".to_string()
+//response data as json if it can be parsed
+fn data_value(data: &[u8]) -> Value{
+    return match serde_json::from_slice(data){
+        Ok(v) => v,
+        Err(_e) => Value::String(printable(data))
     };
-    if key_str.is_empty() {
-        for a in key.iter() {
-            write!(key_str, "{:02x}", a).expect("Not written");
+}
+
+pub fn logger_storage_event_insert(accounts: &accounts::Accounts, key: &[u8], value: &[u8]){
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
+    let key_str = printable(key);
+    let val_str = printable(value);
+    if is_json_output() {
+        return emit(json!({"event": "storage", "op": "insert", "key": key_str, "value": val_str}));
+    }
+    println!("DB Changed : [Insert]\nKey        : [{}]\nValue      : [{}]",accounts::label(accounts,key_str.as_str()),accounts::label(accounts,val_str.as_str()));
+}
+
+pub fn logger_storage_event_remove(accounts: &accounts::Accounts, key: &[u8]){
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
+    let key_str = printable(key);
+    if is_json_output() {
+        return emit(json!({"event": "storage", "op": "remove", "key": key_str}));
+    }
+    println!("DB Changed : [Remove]\nKey        : [{}]",accounts::label(accounts,key_str.as_str()));
+}
+
+pub fn logger_unmatched_query(accounts: &accounts::Accounts, contract: &str, query: &str){
+    if is_json_output() {
+        return emit(json!({"event": "unmatched_query", "contract": contract, "query": query}));
+    }
+    println!("Unmatched  : wasm query to [{}]\nQuery      : [{}]",accounts::label(accounts,contract),accounts::label(accounts,query));
+}
+
+//message returned by contract that simulation does not model, like WasmMsg
+pub fn logger_unhandled_message(accounts: &accounts::Accounts, msg: &Value){
+    if is_json_output() {
+        return emit(json!({"event": "unhandled_message", "msg": msg}));
+    }
+    println!("Unhandled  : message skipped\nMessage    : [{}]",accounts::label(accounts,msg.to_string().as_str()));
+}
+
+pub fn logger_call_start(accounts: &accounts::Accounts, call_type: &str, sender: &str, msg: &str){
+    if is_json_output() {
+        return emit(json!({"event": "call_start", "call_type": call_type, "sender": sender, "msg": data_value(msg.as_bytes())}));
+    }
+    println!("***************************call started***************************");
+    println!("executing func [{}] , sender is {} , params is {}",call_type,accounts::label(accounts,sender),msg);
+}
+
+//log attributes are key and value pairs, messages are json of CosmosMsg
+pub fn logger_call_result(accounts: &accounts::Accounts, call_type: &str, data: &[u8], log: &Vec<(String,String)>, messages: &Vec<Value>){
+    if is_json_output() {
+        let log : Vec<Value> = log.iter().map(|l| json!({"key": l.0, "value": l.1})).collect();
+        return emit(json!({"event": "result", "call_type": call_type, "data": data_value(data), "log": log, "messages": messages}));
+    }
+    println!("{} msg.data: = {}",call_type,accounts::label(accounts,printable(data).as_str()));
+    for l in log {
+        println!("Log        : {} = {}",l.0,accounts::label(accounts,l.1.as_str()));
+    }
+    for m in messages {
+        println!("Message    : {}",accounts::label(accounts,m.to_string().as_str()));
+    }
+}
+
+//query response checked against response schema, pretty json or mismatches
+pub fn logger_query_response(accounts: &accounts::Accounts, response: &Result<String,Vec<String>>){
+    if is_json_output() {
+        return match response {
+            Ok(_) => {},
+            Err(errors) => emit(json!({"event": "response_mismatch", "errors": errors}))
+        };
+    }
+    match response {
+        Ok(pretty) => println!("Response   :\n{}",accounts::label(accounts,pretty.as_str())),
+        Err(errors) => {
+            for e in errors {
+                println!("Response mismatch : {}",e);
+            }
         }
     }
+}
+
+pub fn logger_call_error(call_type: &str, error: &str){
+    if is_json_output() {
+        return emit(json!({"event": "error", "call_type": call_type, "error": error}));
+    }
+    println!("Error {}",error);
+}
 
-    println!("DB Changed : [Remove]\nKey        : [{}]",key_str);
-}
\ No newline at end of file
+pub fn logger_gas_used(gas_used: u64){
+    if is_json_output() {
+        return emit(json!({"event": "gas", "gas_used": gas_used}));
+    }
+    println!("Gas used   : {}",gas_used);
+    println!("***************************call finished***************************");
+}
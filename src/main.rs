@@ -1,32 +1,35 @@
 
 extern crate clap;
+#[macro_use]
+extern crate lazy_static;
 
 pub mod contract_vm;
 
-use clap::{Arg, App};
-use std::io;
+use clap::{Arg, App, AppSettings, ArgMatches, SubCommand};
 use std::ops::Add;
 use crate::contract_vm::engine::ContractInstance;
+use crate::contract_vm::settings::Settings;
+use crate::contract_vm::session::CommandResult;
 
 
 fn show_message_type(name : &str,members : &Vec<contract_vm::analyzer::Member>,engine : &contract_vm::engine::ContractInstance){
-    println!("{} {{",name);
+    contract_vm::watcher::logger_info(&format!("{} {{",name));
     for vcm in members {
         let st = match engine.analyzer.map_of_struct.get_key_value(vcm.member_def.as_str()){
             Some(h) => h,
             _ => {
-                println!("\t{} : {}",vcm.member_name,vcm.member_def);
+                contract_vm::watcher::logger_info(&format!("\t{} : {}",vcm.member_name,vcm.member_def));
                 continue;
             }
         };
         //todo:need show all members by recursive invocation
-        println!("\t{} : {} :{{ ",vcm.member_name,vcm.member_def);
+        contract_vm::watcher::logger_info(&format!("\t{} : {} :{{ ",vcm.member_name,vcm.member_def));
         for members in st.1 {
-            println!("\t\t{} : {}",members.0,members.1);
+            contract_vm::watcher::logger_info(&format!("\t\t{} : {}",members.0,members.1));
         }
-        println!("\t}}")
+        contract_vm::watcher::logger_info("\t}")
     }
-    println!("}}");
+    contract_vm::watcher::logger_info("}");
 }
 
 fn check_is_need_flag(name : &str) -> bool{
@@ -52,22 +55,15 @@ fn to_json_item(name : &String,data : &String,type_name : &str) -> String{
 }
 
 fn input_with_out_handle(input_data : &mut String) -> bool{
-    match io::stdin().read_line(input_data) {
-        Ok(_n) => {
-            if input_data.ends_with("\n") {
-                input_data.remove(input_data.len() - 1);
-            }
-        }
-        Err(error) => {
-            println!("error: {}", error);
-            return false;
-        },
+    match contract_vm::console::read_line() {
+        Some(line) => input_data.push_str(line.as_str()),
+        None => return false
     }
     return true;
 }
 
 fn input_type(mem_name : &String, type_name : &String, engine : &contract_vm::engine::ContractInstance) -> String{
-    println!("input [{}]:",mem_name);
+    contract_vm::watcher::logger_info(&format!("input [{}]:",mem_name));
     let st = match engine.analyzer.map_of_struct.get_key_value(type_name){
         Some(h) => h,
         _ => {
@@ -81,7 +77,7 @@ fn input_type(mem_name : &String, type_name : &String, engine : &contract_vm::en
     params += mem_name;
     params += "\":[{";
     for members in st.1 {
-        println!("input \t[{} : {}]:",members.0,members.1);
+        contract_vm::watcher::logger_info(&format!("input \t[{} : {}]:",members.0,members.1));
         let mut single : String = String::new();
         input_with_out_handle(&mut single);
         params += to_json_item(&members.0,&single,type_name).as_str();
@@ -115,55 +111,68 @@ fn input_message(name : &str, members : &Vec<contract_vm::analyzer::Member>, eng
         final_msg = final_msg.add("}");
     }
 
-    println!("JsonMsg:{}",final_msg);
+    contract_vm::watcher::logger_info(&format!("JsonMsg:{}",final_msg));
     return final_msg;
 }
 
 fn simulate_by_auto_analyze(engine : &mut ContractInstance){
 
+    engine.analyzer.dump_all_roles();
     engine.analyzer.dump_all_members();
     loop {
         let mut is_enum = false;
         let mut call_type = String::new();
         let mut call_param = String::new();
-        println!("Input call type(init | handle | query):");
-        input_with_out_handle(&mut call_type);
-        if call_type.ne("init") && call_type.ne("handle") && call_type.ne("query") {
-            println!("Wrong call type[{}], must one of (init | handle | query)",call_type);
+        contract_vm::watcher::logger_info("Input call type(init | handle | query), :help for commands:");
+        if !input_with_out_handle(&mut call_type) {
+            break;
+        }
+        match contract_vm::session::run_command(engine,call_type.as_str()) {
+            CommandResult::Quit => break,
+            CommandResult::Done => continue,
+            CommandResult::NotCommand => {}
+        }
+        if call_type.trim().is_empty() {
             continue;
         }
-        print!("Input Call param from [ ");
-        for k in engine.analyzer.map_of_member.keys(){
-            print!("{} | ",k);
+        if call_type.ne("init") && call_type.ne("handle") && call_type.ne("query") {
+            contract_vm::watcher::logger_info(&format!("Wrong call type[{}], must one of (init | handle | query)",call_type));
+            continue;
         }
-        print!(" ]\n");
-        input_with_out_handle(&mut call_param);
-
-        let msg_type = match engine.analyzer.map_of_member.get(call_param.as_str()){
+        //message type is implied by call type, only variants need to be chosen
+        let title = match engine.analyzer.message_title_of(call_type.as_str()){
             None => {
-                println!("can not find msg type {}",call_param.as_str());
+                contract_vm::watcher::logger_info(&format!("can not find {} message in schema",call_type.as_str()));
+                continue;
+            },
+            Some(t) => t
+        };
+        let msg_type = match engine.analyzer.map_of_member.get(title.as_str()){
+            None => {
+                contract_vm::watcher::logger_info(&format!("can not find msg type {}",title.as_str()));
                 continue;
             },
             Some(v) => v
         };
-        let len = msg_type.len();
-        if  len > 1 {
-            //only one msg
+        if engine.analyzer.is_enum_message(title.as_str()) {
             is_enum = true;
 
-            print!("Input Call param from [ ");
+            let mut choices = "Input Call param from [ ".to_string();
             for k in msg_type.keys(){
-                print!("{} | ",k);
+                choices += format!("{} | ",k).as_str();
             }
-            print!(" ]\n");
-            call_param.clear();
-            input_with_out_handle(&mut call_param);
+            choices += " ]";
+            contract_vm::watcher::logger_info(choices.as_str());
+            if !input_with_out_handle(&mut call_param) {
+                break;
+            }
+        }else {
+            call_param = title.clone();
         }
 
-
         let msg = match msg_type.get(call_param.as_str()){
             None => {
-                println!("can not find msg type {}",call_param.as_str());
+                contract_vm::watcher::logger_info(&format!("can not find msg type {}",call_param.as_str()));
                 continue;
             },
             Some(v) => v
@@ -173,7 +182,9 @@ fn simulate_by_auto_analyze(engine : &mut ContractInstance){
         let json_msg = input_message(call_param.as_str(),msg,&engine,&is_enum);
 
         let result = engine.call(call_type,json_msg);
-        println!("Call return msg [{}]",result);
+        if !contract_vm::watcher::is_json_output() {
+            println!("Call return msg [{}]",result);
+        }
     }
 }
 
@@ -181,67 +192,666 @@ fn simulate_by_json(engine : &mut ContractInstance){
     loop {
         let mut call_type = String::new();
         let mut json_msg = String::new();
-        println!("Input call type(init | handle | query):");
-        input_with_out_handle(&mut call_type);
+        contract_vm::watcher::logger_info("Input call type(init | handle | query), :help for commands:");
+        if !input_with_out_handle(&mut call_type) {
+            break;
+        }
+        match contract_vm::session::run_command(engine,call_type.as_str()) {
+            CommandResult::Quit => break,
+            CommandResult::Done => continue,
+            CommandResult::NotCommand => {}
+        }
+        if call_type.trim().is_empty() {
+            continue;
+        }
         if call_type.ne("init") && call_type.ne("handle") && call_type.ne("query") {
-            println!("Wrong call type[{}], must one of (init | handle | query)",call_type);
+            contract_vm::watcher::logger_info(&format!("Wrong call type[{}], must one of (init | handle | query)",call_type));
             continue;
         }
-        println!("Input json string:");
-        input_with_out_handle(&mut json_msg);
+        contract_vm::watcher::logger_info("Input json string:");
+        if !input_with_out_handle(&mut json_msg) {
+            break;
+        }
+        if let Err(errors) = engine.analyzer.validate_message(call_type.as_str(),json_msg.as_str()) {
+            for e in &errors {
+                contract_vm::watcher::logger_info(&format!("Schema error : {}",e));
+            }
+            contract_vm::watcher::logger_info("Message does not match schema, send anyway? (y | n)");
+            let mut answer = String::new();
+            input_with_out_handle(&mut answer);
+            if answer.ne("y") {
+                continue;
+            }
+        }
         let result = engine.call(call_type,json_msg);
-        println!("Call return msg [{}]",result);
+        if !contract_vm::watcher::is_json_output() {
+            println!("Call return msg [{}]",result);
+        }
     }
 }
 
-fn start_simulate(wasmfile:&str) -> Result<bool,String>{
-    println!("loading {}",wasmfile);
-    let mut engine = match contract_vm::build_simulation(wasmfile) {
-        Err(e) => return Err(e),
-        Ok(instance) => instance,
-    };
+//options shared by every subcommand
+struct SharedOptions {
+    settings : Settings,
+    schema_dirs : Vec<String>,
+    responses : Vec<String>,
+}
+
+//wasm binaries start with magic `\0asm`, whatever their file name is
+fn check_wasm_file(file : &str) -> Result<(),String>{
+    let data = contract_vm::analyzer::load_data_from_file(file)?;
+    if data.len() < 8 || &data[..4] != b"\0asm" {
+        return Err(format!("{} is not a wasm binary",file));
+    }
+    return Ok(());
+}
+
+//build contract instance, map query responses and load json schema, return whether schema is found
+fn load_contract(file : &str,shared : &SharedOptions) -> Result<(ContractInstance,bool),String>{
+    check_wasm_file(file)?;
+    contract_vm::watcher::logger_info(&format!("loading {}",file));
+    let mut engine = contract_vm::build_simulation(file,&shared.settings)?;
+    for r in &shared.responses {
+        let kv : Vec<&str> = r.splitn(2,'=').collect();
+        if kv.len() != 2 {
+            return Err(format!("wrong response mapping [{}], must be like balance=BalanceResponse",r));
+        }
+        engine.analyzer.set_response_type(kv[0],kv[1]);
+    }
+    let wasm_file = engine.wasm_file.clone();
+    let has_schema = engine.analyzer.auto_load_json_schema(wasm_file.as_str(),&shared.schema_dirs);
+    return Ok((engine,has_schema));
+}
 
+fn start_simulate(file:&str,shared:&SharedOptions,raw_json:bool,record:Option<&str>) -> Result<bool,String>{
+    let (mut engine,has_schema) = load_contract(file,shared)?;
     engine.show_module_info();
-    if engine.analyzer.auto_load_json_schema(&engine.wasm_file){
+    if let Some(f) = record {
+        engine.recorder = Some(contract_vm::scenario::Recorder::new(f,&shared.settings)?);
+    }
+    contract_vm::console::start(contract_vm::session::completion_words(&engine));
+    if has_schema && !raw_json{
         simulate_by_auto_analyze(&mut engine);
     }else{
+        contract_vm::watcher::logger_info("Running in raw json mode");
         simulate_by_json(&mut engine);
     }
     return Ok(true);
+}
+
+//init contract before batch runs, use template of InitMsg if no init message given
+fn init_for_batch(engine : &mut ContractInstance,init_msg : Option<&str>) -> Result<(),String>{
+    let msg = match init_msg {
+        Some(m) => m.to_string(),
+        None => {
+            let schema = match engine.analyzer.message_title_of("init").and_then(|t| engine.analyzer.map_of_schema.get(&t)){
+                None => return Err("no InitMsg schema found, use --init to set init message".to_string()),
+                Some(s) => s
+            };
+            match contract_vm::template::message_variants(schema).first() {
+                None => return Err("can not build init message from schema".to_string()),
+                Some(v) => v.1.to_string()
+            }
+        }
+    };
+    contract_vm::watcher::logger_info(&format!("init with {}",msg));
+    if let Err(e) = engine.execute("init",msg.as_str()) {
+        return Err(format!("init failed, {}",e));
+    }
+    return Ok(());
+}
+
+fn fuzz_contract(file:&str,shared:&SharedOptions,init_msg:Option<&str>,iterations:usize,seed:u64) -> Result<bool,String>{
+    let (mut engine,has_schema) = load_contract(file,shared)?;
+    if !has_schema {
+        return Err("fuzzing needs json schema".to_string());
+    }
+    init_for_batch(&mut engine,init_msg)?;
+    contract_vm::watcher::logger_info(&format!("fuzzing {} messages with seed {}",iterations,seed));
+    let findings = contract_vm::fuzzer::run_fuzz(&mut engine,iterations,seed);
+    contract_vm::fuzzer::print_findings(&findings);
+    return Ok(findings.is_empty());
+}
+
+//every scenario runs against a fresh instance, built from its own setup if it has one
+fn run_scenarios(file:&str,shared:&SharedOptions,scenario_files:Vec<&str>,results_file:Option<&str>) -> Result<bool,String>{
+    let mut all_passed = true;
+    let mut all_results = Vec::new();
+    for scenario_file in scenario_files {
+        let scenario = contract_vm::scenario::load_scenario(scenario_file)?;
+        let (mut engine,_has_schema) = match &scenario.setup {
+            None => load_contract(file,shared)?,
+            Some(setup) => {
+                let mut settings = Settings::default();
+                setup.apply(&mut settings)?;
+                load_contract(file,&SharedOptions{
+                    settings,
+                    schema_dirs: shared.schema_dirs.clone(),
+                    responses: shared.responses.clone()
+                })?
+            }
+        };
+        contract_vm::watcher::logger_info(&format!("running scenario {}",scenario_file));
+        let results = contract_vm::scenario::run_scenario(&mut engine,&scenario)?;
+        all_passed &= contract_vm::scenario::print_summary(&results);
+        all_results.extend(results);
+    }
+    if let Some(f) = results_file {
+        contract_vm::scenario::save_results(&all_results,f)?;
+        contract_vm::watcher::logger_info(&format!("results saved to {}",f));
+    }
+    return Ok(all_passed);
+}
+
+fn probe_contract(file:&str,shared:&SharedOptions) -> Result<bool,String>{
+    let (mut engine,has_schema) = load_contract(file,shared)?;
+    if !has_schema {
+        return Err("probing needs json schema".to_string());
+    }
+    let issues = contract_vm::probe::run_probe(&mut engine);
+    contract_vm::probe::print_issues(&issues);
+    //vm errors are reported, only drift fails the probe
+    return Ok(issues.iter().all(|i| !i.drift));
+}
+
+fn generate_templates(wasmfile:&str,schema_dirs:&Vec<String>,dir:&str) -> Result<bool,String>{
+    let mut analyzer = contract_vm::analyzer::Analyzer::default();
+    if !analyzer.auto_load_json_schema(wasmfile,schema_dirs) {
+        return Err("no json schema found".to_string());
+    }
+    let count = contract_vm::template::write_templates(&analyzer,dir)?;
+    contract_vm::watcher::logger_info(&format!("{} templates generated",count));
+    return Ok(true);
+}
 
+fn inspect_contract(file:&str,shared:&SharedOptions) -> Result<bool,String>{
+    let (engine,has_schema) = load_contract(file,shared)?;
+    engine.show_module_info();
+    if has_schema {
+        engine.analyzer.dump_all_roles();
+        engine.analyzer.dump_all_members();
+    }
+    return Ok(true);
+}
+
+//message given on command line, `@file` reads it from file
+fn message_arg(msg : &str) -> Result<String,String>{
+    if msg.starts_with('@') {
+        let data = contract_vm::analyzer::load_data_from_file(&msg[1..])?;
+        return match String::from_utf8(data) {
+            Err(_e) => Err(format!("message file {} is not utf8",&msg[1..])),
+            Ok(text) => Ok(text.trim().to_string())
+        };
+    }
+    return Ok(msg.to_string());
 }
 
-fn prepare_command_line() -> bool{
+//init, handle and query in the order given on command line
+fn calls_in_order<'a>(matches : &'a ArgMatches) -> Vec<(usize,&'static str,&'a str)>{
+    let mut calls = Vec::new();
+    for call_type in &["init","handle","query"] {
+        if let (Some(values),Some(indices)) = (matches.values_of(call_type),matches.indices_of(call_type)) {
+            for (msg,idx) in values.zip(indices) {
+                calls.push((idx,*call_type,msg));
+            }
+        }
+    }
+    calls.sort_by_key(|c| c.0);
+    return calls;
+}
+
+//run calls against one fresh instance, stop at the first failed call
+fn call_contract(file:&str,shared:&SharedOptions,calls:Vec<(usize,&str,&str)>) -> Result<bool,String>{
+    if calls.is_empty() {
+        return Err("nothing to call, use --init, --handle or --query".to_string());
+    }
+    let mut messages = Vec::new();
+    for (_idx,call_type,msg) in calls {
+        messages.push((call_type,message_arg(msg)?));
+    }
+    let (mut engine,_has_schema) = load_contract(file,shared)?;
+    for (call_type,msg) in messages {
+        if !engine.run_call(call_type,msg.as_str()) {
+            return Ok(false);
+        }
+    }
+    return Ok(true);
+}
+
+fn serve_contract(file:&str,shared:&SharedOptions,address:&str) -> Result<bool,String>{
+    let (mut engine,_has_schema) = load_contract(file,shared)?;
+    contract_vm::server::serve(&mut engine,address)?;
+    return Ok(true);
+}
+
+//flags of simulation shared by every subcommand
+fn shared_args<'a,'b>() -> Vec<Arg<'a,'b>>{
+    return vec![
+        Arg::with_name("config")
+            .long("config")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("toml file with defaults of the flags below, simulate.toml of working directory is used if not set"),
+        Arg::with_name("chain-id")
+            .long("chain-id")
+            .value_name("ID")
+            .takes_value(true)
+            .help("chain id of env, okchain if not set"),
+        Arg::with_name("block-height")
+            .long("block-height")
+            .value_name("HEIGHT")
+            .takes_value(true)
+            .help("block height of env before the first call, 0 if not set"),
+        Arg::with_name("block-time")
+            .long("block-time")
+            .value_name("SECONDS")
+            .takes_value(true)
+            .help("block time of env in seconds before the first call, 0 if not set"),
+        Arg::with_name("contract-address")
+            .long("contract-address")
+            .value_name("ADDRESS")
+            .takes_value(true)
+            .help("address of the simulated contract, generated if not set"),
+        Arg::with_name("schema")
+            .long("schema")
+            .value_name("DIR")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("directory of json schema files, can be used more than once, searched beside the wasm file if not set"),
+        Arg::with_name("response")
+            .long("response")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("response type of query variant like balance=BalanceResponse, inferred from variant name if not set"),
+        Arg::with_name("output")
+            .long("output")
+            .value_name("FORMAT")
+            .takes_value(true)
+            .possible_values(&["text","json"])
+            .help("text output for reading, or json to write one json object per event"),
+        Arg::with_name("output-file")
+            .long("output-file")
+            .value_name("FILE")
+            .takes_value(true)
+            .requires("output")
+            .help("write json events to file instead of stdout"),
+        Arg::with_name("address-prefix")
+            .long("address-prefix")
+            .value_name("HRP")
+            .takes_value(true)
+            .help("use bech32 addresses with human readable prefix HRP like cosmos, raw addresses if not set"),
+        Arg::with_name("canonical-length")
+            .long("canonical-length")
+            .value_name("LEN")
+            .takes_value(true)
+            .help("length in bytes of canonical address, 20 if not set"),
+        Arg::with_name("account")
+            .long("account")
+            .value_name("ACCOUNT")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("named account like alice, alice=ADDR or alice=ADDR:100okt, refer to it as @alice in messages"),
+        Arg::with_name("sender")
+            .long("sender")
+            .value_name("SENDER")
+            .takes_value(true)
+            .help("account alias or address of message sender"),
+        Arg::with_name("balance")
+            .long("balance")
+            .value_name("BALANCE")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("bank balance of account or address like alice=100okt,5uatom, visible to contract by bank queries"),
+        Arg::with_name("funds")
+            .long("funds")
+            .value_name("COINS")
+            .takes_value(true)
+            .help("funds like 100okt attached to every init and handle, moved from sender to contract"),
+        Arg::with_name("validator")
+            .long("validator")
+            .value_name("VALIDATOR")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("validator alias or address with optional commission percent like val1:5"),
+        Arg::with_name("delegation")
+            .long("delegation")
+            .value_name("DELEGATION")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("starting delegation like alice:val1:1000, amount in bonded denom"),
+        Arg::with_name("bonded-denom")
+            .long("bonded-denom")
+            .value_name("DENOM")
+            .takes_value(true)
+            .help("denom of staking, default is stake"),
+        Arg::with_name("reward-rate")
+            .long("reward-rate")
+            .value_name("PPM")
+            .takes_value(true)
+            .help("staking rewards per block in parts per million of delegated amount, default is 100"),
+        Arg::with_name("custom-fixture")
+            .long("custom-fixture")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with("custom-process")
+            .help("json fixture answering custom queries and messages of chain modules"),
+        Arg::with_name("custom-process")
+            .long("custom-process")
+            .value_name("COMMAND")
+            .takes_value(true)
+            .help("external process answering custom queries and messages, one json per line over stdin/stdout"),
+        Arg::with_name("contract-fixture")
+            .long("contract-fixture")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("json fixture answering smart and raw queries to contracts that are not loaded"),
+        Arg::with_name("gas-limit")
+            .long("gas-limit")
+            .value_name("GAS")
+            .takes_value(true)
+            .help("gas limit of every call, 500000 if not set")
+    ];
+}
+
+fn parse_shared(matches : &ArgMatches) -> Result<SharedOptions,String>{
+    if matches.value_of("output") == Some("json") {
+        contract_vm::watcher::set_json_output(matches.value_of("output-file"))?;
+    }
+    let mut settings = Settings::default();
+    let config = contract_vm::config::find_config(matches.value_of("config"))?.unwrap_or_default();
+    config.apply(&mut settings)?;
+    //flags override config, flags that can be repeated add to it
+    if let Some(prefix) = matches.value_of("address-prefix") {
+        settings.address_prefix = Some(prefix.to_string());
+    }
+    if let Some(chain_id) = matches.value_of("chain-id") {
+        settings.chain_id = chain_id.to_string();
+    }
+    if let Some(height) = matches.value_of("block-height") {
+        settings.block_height = height.parse::<u64>().map_err(|_e| format!("wrong block height {}",height))?;
+    }
+    if let Some(time) = matches.value_of("block-time") {
+        settings.block_time = time.parse::<u64>().map_err(|_e| format!("wrong block time {}",time))?;
+    }
+    if let Some(address) = matches.value_of("contract-address") {
+        settings.contract_address = Some(address.to_string());
+    }
+    if let Some(len) = matches.value_of("canonical-length") {
+        settings.canonical_length = match len.parse::<usize>(){
+            Ok(l) if l > 0 => l,
+            _ => return Err(format!("wrong canonical length {}",len))
+        };
+    }
+    if let Some(gas) = matches.value_of("gas-limit") {
+        settings.gas_limit = match gas.parse::<u64>(){
+            Ok(g) if g > 0 => g,
+            _ => return Err(format!("wrong gas limit {}",gas))
+        };
+    }
+    if let Some(defs) = matches.values_of("account") {
+        for def in defs {
+            settings.add_account(def)?;
+        }
+    }
+    if let Some(sender) = matches.value_of("sender") {
+        settings.sender = Some(sender.to_string());
+    }
+    if let Some(funds) = matches.value_of("funds") {
+        settings.funds = contract_vm::accounts::parse_coins(funds)?;
+    }
+    if let Some(defs) = matches.values_of("balance") {
+        for def in defs {
+            settings.add_balance(def)?;
+        }
+    }
+    if let Some(defs) = matches.values_of("validator") {
+        for def in defs {
+            settings.add_validator(def)?;
+        }
+    }
+    if let Some(defs) = matches.values_of("delegation") {
+        for def in defs {
+            settings.add_delegation(def)?;
+        }
+    }
+    if let Some(denom) = matches.value_of("bonded-denom") {
+        settings.bonded_denom = Some(denom.to_string());
+    }
+    if let Some(rate) = matches.value_of("reward-rate") {
+        settings.reward_rate = match rate.parse::<u128>(){
+            Ok(r) => Some(r),
+            Err(_e) => return Err(format!("wrong reward rate {}",rate))
+        };
+    }
+    //custom fixture and process exclude each other, the one of command line wins
+    if let Some(file) = matches.value_of("custom-fixture") {
+        settings.custom_fixture = Some(file.to_string());
+        settings.custom_process = None;
+    }
+    if let Some(command) = matches.value_of("custom-process") {
+        settings.custom_process = Some(command.to_string());
+        settings.custom_fixture = None;
+    }
+    if let Some(file) = matches.value_of("contract-fixture") {
+        settings.contract_fixture = Some(file.to_string());
+    }
+    let schema_dirs = match matches.values_of("schema") {
+        None => config.schema.clone().unwrap_or_default(),
+        Some(dirs) => dirs.map(|d| d.to_string()).collect()
+    };
+    let mut responses = config.response.clone().unwrap_or_default();
+    if let Some(defs) = matches.values_of("response") {
+        responses.extend(defs.map(|r| r.to_string()));
+    }
+    return Ok(SharedOptions{
+        settings,
+        schema_dirs,
+        responses
+    });
+}
+
+fn wasm_arg<'a,'b>() -> Arg<'a,'b>{
+    return Arg::with_name("wasm")
+        .help("contract file that built by https://github.com/CosmWasm/rust-optimizer")
+        .required(true)
+        .empty_values(false);
+}
+
+//Ok(false) means the run found problems, Err means it could not run
+fn prepare_command_line() -> Result<bool,String>{
     let matches = App::new("cosmwasm-simulate")
         .version("0.1.0")
         .author("github : https://github.com/KamiD")
         .about("A simulation of cosmwasm smart contract system")
-        .arg(Arg::with_name("run")
-            .help("contract file that built by https://github.com/CosmWasm/rust-optimizer")
-            .empty_values(false)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("repl")
+            .alias("run")
+            .about("start interactive session")
+            .arg(wasm_arg())
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("input raw json messages instead of guided input, messages are still validated by schema if found")
+            )
+            .arg(Arg::with_name("record")
+                .long("record")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("record every call of the session to a yaml or json scenario file, replay it with test")
+            )
+            .args(&shared_args())
+        )
+        .subcommand(SubCommand::with_name("call")
+            .about("run init, handle and query messages in the given order against a fresh instance")
+            .arg(wasm_arg())
+            .arg(Arg::with_name("init")
+                .long("init")
+                .value_name("JSON")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("init message, or @file to read it from file")
+            )
+            .arg(Arg::with_name("handle")
+                .long("handle")
+                .value_name("JSON")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("handle message, or @file to read it from file")
+            )
+            .arg(Arg::with_name("query")
+                .long("query")
+                .value_name("JSON")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("query message, or @file to read it from file")
+            )
+            .args(&shared_args())
+        )
+        .subcommand(SubCommand::with_name("inspect")
+            .about("show module info and json schema of contract")
+            .arg(wasm_arg())
+            .arg(Arg::with_name("gen-templates")
+                .long("gen-templates")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("write skeleton json message of every InitMsg, HandleMsg and QueryMsg variant into DIR (or stdout with -) and exit")
+            )
+            .args(&shared_args())
+        )
+        .subcommand(SubCommand::with_name("test")
+            .about("run scenario files, fuzz or probe contract, exit code is 1 if anything failed")
+            .arg(wasm_arg())
+            .arg(Arg::with_name("scenario")
+                .help("yaml or json scenario files, each runs against a fresh instance")
+                .multiple(true)
+            )
+            .arg(Arg::with_name("results")
+                .long("results")
+                .value_name("FILE")
+                .takes_value(true)
+                .requires("scenario")
+                .help("save results of scenario steps to json file")
+            )
+            .arg(Arg::with_name("fuzz")
+                .long("fuzz")
+                .value_name("N")
+                .takes_value(true)
+                .conflicts_with("scenario")
+                .help("send N random schema-valid handle and query messages, report traps, panics, out of gas and non-deterministic results")
+            )
+            .arg(Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .takes_value(true)
+                .requires("fuzz")
+                .help("random seed of --fuzz, current time if not set")
+            )
+            .arg(Arg::with_name("init")
+                .long("init")
+                .value_name("JSON")
+                .takes_value(true)
+                .requires("fuzz")
+                .help("init message sent before --fuzz, template of InitMsg if not set")
+            )
+            .arg(Arg::with_name("probe")
+                .long("probe")
+                .conflicts_with_all(&["scenario","fuzz"])
+                .help("send one message per schema variant and report variants the contract can not parse")
+            )
+            .args(&shared_args())
+        )
+        .subcommand(SubCommand::with_name("serve")
+            .about("serve contract over websocket, every text message is a scenario step answered by its result")
+            .arg(wasm_arg())
+            .arg(Arg::with_name("listen")
+                .long("listen")
+                .value_name("ADDR")
+                .takes_value(true)
+                .default_value("127.0.0.1:8080")
+                .help("address to listen on")
+            )
+            .args(&shared_args())
+        )
+        .subcommand(SubCommand::with_name("address")
+            .about("print valid test addresses generated from seeds")
+            .arg(Arg::with_name("seed")
+                .help("seed of address")
+                .required(true)
+                .multiple(true)
+            )
+            .args(&shared_args())
         )
         .get_matches();
 
-    if let Some(file) = matches.value_of("run"){
-        if !file.ends_with(".wasm") {
-            println!("only support file[*.wasm], you just input a wrong file format - {:?}",file);
-            return false;
-        }
-        match start_simulate(file) {
-            Ok(t) => {
-                if t{
-                    println!("start_simulate success");
+    let (name,sub) = matches.subcommand();
+    let sub = match sub {
+        None => return Ok(false),
+        Some(m) => m
+    };
+    let shared = parse_shared(sub)?;
+    let file = sub.value_of("wasm").unwrap_or_default();
+    return match name {
+        "repl" => start_simulate(file,&shared,sub.is_present("json"),sub.value_of("record")),
+        "call" => call_contract(file,&shared,calls_in_order(sub)),
+        "inspect" => match sub.value_of("gen-templates") {
+            Some(dir) => generate_templates(file,&shared.schema_dirs,dir),
+            None => inspect_contract(file,&shared)
+        },
+        "test" => {
+            if sub.is_present("probe") {
+                return probe_contract(file,&shared);
+            }
+            if let Some(n) = sub.value_of("fuzz") {
+                let iterations = match n.parse::<usize>(){
+                    Err(_e) => return Err(format!("wrong fuzz iterations {}",n)),
+                    Ok(i) => i
+                };
+                let seed = match sub.value_of("seed") {
+                    Some(s) => match s.parse::<u64>(){
+                        Err(_e) => return Err(format!("wrong seed {}",s)),
+                        Ok(v) => v
+                    },
+                    None => std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+                };
+                return fuzz_contract(file,&shared,sub.value_of("init"),iterations,seed);
+            }
+            match sub.values_of("scenario") {
+                None => Err("nothing to test, give scenario files, --fuzz or --probe".to_string()),
+                Some(files) => run_scenarios(file,&shared,files.collect(),sub.value_of("results"))
+            }
+        },
+        "serve" => serve_contract(file,&shared,sub.value_of("listen").unwrap_or_default()),
+        "address" => {
+            let api = shared.settings.build_api()?;
+            for seed in sub.values_of("seed").unwrap() {
+                let address = api.test_address(seed);
+                if contract_vm::watcher::is_json_output() {
+                    contract_vm::watcher::logger_report("address",serde_json::json!({"seed": seed, "address": address}));
                 }else {
-                    println!("start_simulate failed")
+                    println!("{} => {}",seed,address);
                 }
-            },
-            Err(e) => println!("error occurred during call start_simulate : {}",e)
-        }
-        return true;
-    }
-    return false;
+            }
+            Ok(true)
+        },
+        _ => Ok(false)
+    };
 }
 
 fn main() {
-    prepare_command_line();
+    match prepare_command_line() {
+        Ok(true) => {},
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("error : {}",e);
+            std::process::exit(2);
+        }
+    }
 }